@@ -0,0 +1,208 @@
+use crate::model::proof::{Proof};
+use crate::environment::{Environment};
+use crate::find_term::{search_term};
+use crate::search::stats::{SearchOutcome};
+use crate::serial::{flagderiv_output};
+
+
+#[derive(PartialEq,Eq,Debug,Clone,Copy)]
+pub enum Outcome {
+    Solved,
+    Failed,
+    Timeout,
+    Limit
+}
+
+impl Outcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::Solved => "pass",
+            Outcome::Failed => "fail",
+            Outcome::Timeout => "timeout",
+            Outcome::Limit => "limit"
+        }
+    }
+}
+
+pub struct BatchGoal {
+    pub line: usize,
+    pub name: String,
+    pub goal: String
+}
+
+pub struct BatchResult {
+    pub line: usize,
+    pub name: String,
+    pub goal: String,
+    pub outcome: Outcome,
+    pub proof: Option<Proof>,
+    pub message: String,
+    pub expansions: u32,
+    pub millis: f64
+}
+
+fn goal_block(content: &str) -> Option<(&str, &str)> {
+    let (name, goal) = content.strip_prefix("\\goal{")?.split_once('}')?;
+    Some((name.trim(), goal.trim()))
+}
+
+// one goal per line, or a named \goal{name} block that runs until the next blank line
+pub fn batch_goals(text: &str) -> Vec<BatchGoal> {
+    let mut output: Vec<BatchGoal> = vec![];
+    let mut in_block = false;
+    for (idx, line) in text.lines().enumerate() {
+        let content = line.split('%').next().unwrap().trim();
+        if content.is_empty() {
+            in_block = false;
+            continue;
+        }
+        if let Some((name, goal)) = goal_block(content) {
+            output.push(BatchGoal { line: idx + 1, name: name.to_string(), goal: goal.to_string() });
+            in_block = true;
+        } else if in_block {
+            let last = output.last_mut().unwrap();
+            last.goal = format!("{} {}", last.goal, content).trim().to_string();
+        } else {
+            output.push(BatchGoal { line: idx + 1, name: (output.len() + 1).to_string(), goal: content.to_string() });
+        }
+    }
+    output
+}
+
+fn run_goal(goal: &BatchGoal, env: &Environment, infer_sorts: bool) -> BatchResult {
+    let mut result = BatchResult {
+        line: goal.line,
+        name: goal.name.clone(),
+        goal: goal.goal.clone(),
+        outcome: Outcome::Failed,
+        proof: None,
+        message: String::new(),
+        expansions: 0,
+        millis: 0.0
+    };
+    let (ctx, s_type) = match env.parse_goal(&goal.goal, infer_sorts) {
+        Ok(x) => x,
+        Err(msg) => { result.message = msg; return result; }
+    };
+    let (res, stats) = search_term(&s_type, &ctx, &env.defs, &env.search);
+    result.expansions = stats.expansions;
+    result.millis = stats.elapsed.as_secs_f64() * 1000.0;
+    result.outcome = match stats.outcome {
+        SearchOutcome::Solved => Outcome::Solved,
+        SearchOutcome::Timeout => Outcome::Timeout,
+        SearchOutcome::Limit => Outcome::Limit,
+        SearchOutcome::Exhausted | SearchOutcome::Failed => Outcome::Failed
+    };
+    match res {
+        Ok(proof) => { result.proof = Some(proof); },
+        Err(msg) => { result.message = msg; }
+    }
+    result
+}
+
+pub fn run_batch(goals: &[BatchGoal], env: &Environment, infer_sorts: bool) -> Vec<BatchResult> {
+    goals.iter().map(|goal| run_goal(goal, env, infer_sorts)).collect()
+}
+
+pub fn batch_document(results: &[BatchResult], flagderiv: bool) -> Result<String, String> {
+    let mut blocks: Vec<String> = vec![];
+    for res in results {
+        let header = format!("% goal {} (line {}): {}", res.name, res.line, res.goal);
+        let body = match &res.proof {
+            Some(proof) if flagderiv => flagderiv_output(proof)?,
+            Some(proof) => proof.lines.last().unwrap().to_latex(),
+            None => format!("% {}: {}", res.outcome.name(), res.message.replace('\n', "\n% "))
+        };
+        blocks.push(format!("{}\n{}", header, body));
+    }
+    Ok(blocks.join("\n\n") + "\n")
+}
+
+pub fn summary_table(results: &[BatchResult]) -> String {
+    let width = results.iter().map(|r| r.name.len()).max().unwrap_or(0).max(4);
+    let mut lines: Vec<String> = vec![format!("{:>width$} {:>6} {:<8} {:>10} {:>10}",
+                                              "goal", "line", "result", "expansions", "time (ms)")];
+    for res in results {
+        lines.push(format!("{:>width$} {:>6} {:<8} {:>10} {:>10.3}",
+                           res.name, res.line, res.outcome.name(), res.expansions, res.millis));
+    }
+    let count = |o: Outcome| results.iter().filter(|r| r.outcome == o).count();
+    lines.push(format!("passed: {}, failed: {}, timed out: {}, limit reached: {}, total: {}",
+                       count(Outcome::Solved), count(Outcome::Failed), count(Outcome::Timeout),
+                       count(Outcome::Limit), results.len()));
+    lines.join("\n")
+}
+
+pub fn exit_code(results: &[BatchResult]) -> i32 {
+    if results.iter().all(|r| r.outcome == Outcome::Solved) { 0 } else { 1 }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::find_term::{SearchOptions};
+
+    #[test]
+    fn batch_summary() {
+        let text = "% chapter 1\n\
+                    A \\to A\n\
+                    \n\
+                    A : \\ast \\vdash ? : A % not provable\n\
+                    (A \\to A) \\to \\neg \\neg (A \\to A)\n\
+                    A \\to";
+        let goals = batch_goals(text);
+        assert_eq!(goals.iter().map(|x| x.line).collect::<Vec<usize>>(), [2, 4, 5, 6]);
+
+        let env = Environment { search: SearchOptions { limit: 200, ..SearchOptions::standard() }, ..Environment::blank() };
+        let results = run_batch(&goals, &env, false);
+        assert_eq!(results.iter().map(|r| r.outcome).collect::<Vec<Outcome>>(),
+                   [Outcome::Solved, Outcome::Failed, Outcome::Solved, Outcome::Failed]);
+        assert_eq!(results[3].message, "failed to parse: (A \\to)");
+        assert_eq!(exit_code(&results), 1);
+        assert_eq!(exit_code(&results[..1]), 0);
+
+        let doc = batch_document(&results, false).unwrap();
+        assert!(doc.starts_with("% goal 1 (line 2): A \\to A\nA : \\ast \\vdash \\lambda b : A . b : A \\to A\n\n"));
+        assert!(doc.contains("% goal 2 (line 4): A : \\ast \\vdash ? : A\n% fail: "));
+        assert!(batch_document(&results, true).unwrap().contains("\\begin{flagderiv}"));
+
+        let table = summary_table(&results);
+        assert_eq!(table.lines().count(), 6);
+        assert!(table.ends_with("passed: 2, failed: 2, timed out: 0, limit reached: 0, total: 4"));
+    }
+
+    #[test]
+    fn structured_goals() {
+        let text = "\\goal{id} A \\to A\n\
+                    \n\
+                    \\goal{k} % two lines\n\
+                    A : \\ast, B : \\ast\n\
+                    \\vdash ? : A \\to B \\to A\n\
+                    \n\
+                    B \\to B";
+        let goals = batch_goals(text);
+        assert_eq!(goals.iter().map(|x| (x.line, x.name.as_str())).collect::<Vec<(usize, &str)>>(),
+                   [(1, "id"), (3, "k"), (7, "3")]);
+        assert_eq!(goals[1].goal, "A : \\ast, B : \\ast \\vdash ? : A \\to B \\to A");
+
+        let results = run_batch(&goals, &Environment::blank(), false);
+        assert_eq!(exit_code(&results), 0);
+        let doc = batch_document(&results, false).unwrap();
+        assert!(doc.contains("% goal k (line 3): A : \\ast, B : \\ast \\vdash ? : A \\to B \\to A\n"));
+    }
+
+    #[test]
+    fn batch_timeout() {
+        let env = Environment { search: SearchOptions { limit: 2, ..SearchOptions::standard() }, ..Environment::blank() };
+        let results = run_batch(&batch_goals("(A \\to B) \\to (B \\to C) \\to A \\to C"), &env, false);
+        assert_eq!(results[0].outcome, Outcome::Limit);
+        assert!(summary_table(&results).ends_with("passed: 0, failed: 0, timed out: 0, limit reached: 1, total: 1"));
+
+        let env = Environment { search: SearchOptions { timeout: Some(Duration::ZERO), ..SearchOptions::standard() }, ..Environment::blank() };
+        let results = run_batch(&batch_goals("(A \\to B) \\to (B \\to C) \\to A \\to C"), &env, false);
+        assert_eq!(results[0].outcome, Outcome::Timeout);
+        assert!(summary_table(&results).ends_with("passed: 0, failed: 0, timed out: 1, limit reached: 0, total: 1"));
+    }
+}
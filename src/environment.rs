@@ -0,0 +1,533 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::model::def::{Definition};
+use crate::model::statement::{Statement};
+use crate::model::judgement::{Judgement};
+use crate::model::expression::{CCExpression};
+use crate::model::proof::{Proof};
+use crate::model::pts::{PtsSpec};
+use crate::parser::{parse, parse_definition, parse_judgement};
+use crate::find_term::{search_term, SearchOptions};
+use crate::stdlib::{bundled_theory};
+use crate::type_check::{check_proof_in, verify_proof_in};
+use crate::unpack_term::{unpack_term_in, unpack_term_as};
+
+
+fn block_ranges(text: &str) -> Vec<(usize, usize, String)> {
+    let mut output: Vec<(usize, usize, String)> = vec![];
+    let mut current: Option<(usize, usize, String)> = None;
+    for (idx, line) in text.lines().enumerate() {
+        let content = line.split('%').next().unwrap().trim();
+        if content.starts_with("\\import{") {
+            output.extend(current.take());
+            output.push((idx + 1, idx + 1, content.to_string()));
+            continue;
+        }
+        let starts_def = content.contains("\\vartriangleright");
+        if content.is_empty() || (starts_def && current.as_ref().is_some_and(
+                |c| c.2.contains("\\vartriangleright"))) {
+            output.extend(current.take());
+        }
+        if content.is_empty() {
+            continue;
+        }
+        current = match current {
+            Some((start, _, block)) => Some((start, idx + 1, block + " " + content)),
+            None => Some((idx + 1, idx + 1, content.to_string()))
+        };
+    }
+    output.extend(current);
+    output
+}
+
+pub fn theory_blocks(text: &str) -> Vec<(usize, String)> {
+    block_ranges(text).into_iter().map(|(start, _, block)| (start, block)).collect()
+}
+
+fn import_name(block: &str) -> Option<&str> {
+    block.strip_prefix("\\import{")?.strip_suffix("}").map(|x| x.trim())
+}
+
+pub fn sort_context(vars: &[String]) -> Vec<Statement> {
+    let mut output: Vec<Statement> = vec![];
+    for var in vars {
+        let stmt = Statement { subject: CCExpression::Var(var.to_string()), s_type: CCExpression::Star };
+        if !output.contains(&stmt) {
+            output.push(stmt);
+        }
+    }
+    output
+}
+
+fn qualified(module: &str, name: &str) -> String {
+    if module.is_empty() { name.to_string() } else { format!("{}/{}", module, name) }
+}
+
+
+pub struct Environment {
+    pub spec: PtsSpec,
+    pub defs: Vec<Definition>,
+    pub derivations: Vec<Proof>,
+    pub filled: Vec<usize>,
+    pub search: SearchOptions,
+    pub search_path: Vec<PathBuf>,
+    pub loaded: Vec<String>,
+    pub loading: Vec<String>
+}
+
+impl Environment {
+    pub fn blank() -> Environment {
+        Environment {
+            spec: PtsSpec::coc(),
+            defs: vec![],
+            derivations: vec![],
+            filled: vec![],
+            search: SearchOptions::standard(),
+            search_path: vec![],
+            loaded: vec![],
+            loading: vec![]
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Definition> {
+        self.defs.iter().find(|d| d.name == name)
+    }
+
+    pub fn check(&self, def: &Definition) -> Result<Proof, String> {
+        if self.get(&def.name).is_some() {
+            return Err(format!("duplicate definition: {}", def.name));
+        }
+        for (idx, arg) in def.args.iter().enumerate() {
+            if def.args[..idx].contains(arg) {
+                return Err(format!("{}: repeated argument {}", def.name, arg));
+            }
+            if !def.context.iter().any(|stmt| stmt.subject.var_str().as_ref() == Some(arg)) {
+                return Err(format!("{}: argument {} is not in the context", def.name, arg));
+            }
+        }
+
+        let primitive = def.body.subject == CCExpression::Prim;
+        let term = if primitive { &def.body.s_type } else { &def.body.subject };
+        let lines = if primitive {
+            unpack_term_in(&self.spec, term, &def.context, &self.defs)
+        } else {
+            unpack_term_as(&self.spec, term, &def.body.s_type, &def.context, &self.defs)
+        }.map_err(|msg| format!("{}: {}", def.name, msg))?;
+        let found = &lines.last().unwrap().statement.s_type;
+        if primitive && !found.is_sort() {
+            return Err(format!("{}: {} is not a type", def.name, def.body.s_type.to_latex()));
+        }
+        if !primitive && !found.alpha_equiv(&def.body.s_type) {
+            return Err(format!("{}: body has type {}, expected {}", def.name,
+                               found.to_latex(), def.body.s_type.to_latex()));
+        }
+        let refs = check_proof_in(&self.spec, &self.defs, &lines).map_err(
+            |msg| format!("{}: {}", def.name, msg))?;
+        Ok(Proof { lines, refs })
+    }
+
+    fn fill(&self, def: Definition) -> Result<Definition, String> {
+        let (res, stats) = search_term(&def.body.s_type, &def.context, &self.defs, &self.search);
+        match res {
+            Ok(proof) => Ok(Definition {
+                body: Statement {
+                    subject: proof.lines.last().unwrap().statement.subject.clone(),
+                    s_type: def.body.s_type.clone()
+                },
+                ..def
+            }),
+            Err(msg) => Err(format!("{}: no term found for {}: {}\n{}", def.name,
+                                    def.body.s_type.to_latex(), msg, stats.to_report()))
+        }
+    }
+
+    pub fn add(&mut self, def: Definition) -> Result<&Proof, String> {
+        let hole = def.is_hole();
+        let def = if hole { self.fill(def)? } else { def };
+        let proof = self.check(&def)?;
+        if hole {
+            self.filled.push(self.defs.len());
+        }
+        self.defs.push(def);
+        self.derivations.push(proof);
+        Ok(self.derivations.last().unwrap())
+    }
+
+    fn resolve_name(&self, name: &str, module: &str) -> Result<String, String> {
+        for candidate in [qualified(module, name), name.to_string()] {
+            if self.get(&candidate).is_some() {
+                return Ok(candidate);
+            }
+        }
+        let suffix = format!("/{}", name);
+        let matches: Vec<&String> = self.defs.iter().map(|d| &d.name).filter(
+            |n| n.ends_with(&suffix)).collect();
+        match matches.len() {
+            0 => Ok(name.to_string()),
+            1 => Ok(matches[0].clone()),
+            _ => Err(format!("ambiguous definition name: {} ({})", name,
+                             matches.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", ")))
+        }
+    }
+
+    fn qualify_in(&self, ex: &CCExpression, module: &str) -> Result<CCExpression, String> {
+        let mut names: HashMap<String, String> = HashMap::new();
+        for name in ex.def_names() {
+            let full = self.resolve_name(&name, module)?;
+            names.insert(name, full);
+        }
+        Ok(ex.rename_defs(&names))
+    }
+
+    pub fn resolve(&self, ex: &CCExpression) -> Result<CCExpression, String> {
+        self.qualify_in(ex, "")
+    }
+
+    fn qualify_def(&self, def: Definition, module: &str) -> Result<Definition, String> {
+        let mut context: Vec<Statement> = vec![];
+        for stmt in &def.context {
+            context.push(Statement {
+                subject: stmt.subject.clone(),
+                s_type: self.qualify_in(&stmt.s_type, module)?
+            });
+        }
+        Ok(Definition {
+            context,
+            name: qualified(module, &def.name),
+            args: def.args,
+            body: Statement {
+                subject: self.qualify_in(&def.body.subject, module)?,
+                s_type: self.qualify_in(&def.body.s_type, module)?
+            }
+        })
+    }
+
+    fn find_module(&self, name: &str, dir: Option<&Path>) -> Option<PathBuf> {
+        let file = if name.ends_with(".tex") { name.to_string() } else { format!("{}.tex", name) };
+        dir.into_iter().map(|d| d.to_path_buf()).chain(self.search_path.iter().cloned()).map(
+            |d| d.join(&file)).find(|p| p.is_file())
+    }
+
+    pub fn import(&mut self, name: &str, dir: Option<&Path>) -> Result<(), String> {
+        if self.loaded.iter().any(|x| x == name) {
+            return Ok(());
+        }
+        if self.loading.iter().any(|x| x == name) {
+            return Err(format!("import cycle: {} -> {}", self.loading.join(" -> "), name));
+        }
+        self.loading.push(name.to_string());
+        let res = match (self.find_module(name, dir), bundled_theory(name)) {
+            (Some(path), _) => self.add_module_file(&path, name),
+            (None, Some(text)) => self.add_module_text(text, name, None).map_err(
+                |msg| format!("{}: {}", name, msg)),
+            (None, None) => Err(format!("theory not found: {}", name))
+        };
+        self.loading.pop();
+        res?;
+        self.loaded.push(name.to_string());
+        Ok(())
+    }
+
+    fn add_module_text(&mut self, text: &str, module: &str, dir: Option<&Path>) -> Result<(), String> {
+        for (line, block) in theory_blocks(text) {
+            if let Some(name) = import_name(&block) {
+                self.import(name, dir).map_err(|msg| format!("line {}: {}", line, msg))?;
+                continue;
+            }
+            let def = parse_definition(&block).ok_or(
+                format!("line {}: failed to parse definition: {}", line, block))?;
+            let full = self.qualify_def(def, module).map_err(|msg| format!("line {}: {}", line, msg))?;
+            self.add(full).map_err(|msg| format!("line {}: {}", line, msg))?;
+        }
+        Ok(())
+    }
+
+    fn add_module_file(&mut self, path: &Path, module: &str) -> Result<(), String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => self.add_module_text(&text, module, path.parent()).map_err(
+                |msg| format!("{}: {}", path.display(), msg)),
+            Err(e) => Err(format!("failed to read theory ({}): {}", path.display(), e))
+        }
+    }
+
+    pub fn add_text(&mut self, text: &str) -> Result<(), String> {
+        self.add_module_text(text, "", None)
+    }
+
+    pub fn add_file(&mut self, path: &str) -> Result<(), String> {
+        self.add_module_file(Path::new(path), "")
+    }
+
+    pub fn parse_goal(&self, expr: &str, infer_sorts: bool) -> Result<(Vec<Statement>, CCExpression), String> {
+        if !expr.contains("\\vdash") {
+            let s_type = self.resolve(&parse(expr).ok_or(format!("failed to parse: ({})", expr))?)?;
+            return Ok((sort_context(&s_type.free_var()), s_type));
+        }
+        let jdg = parse_judgement(expr).ok_or(format!("failed to parse: ({})", expr))?;
+        if jdg.statement.subject != CCExpression::Var("?".to_string()) {
+            return Err(format!("the subject of the goal must be ?, found {}", jdg.statement.subject.to_latex()));
+        }
+        let jdg = self.resolve_judgement(&jdg, infer_sorts)?;
+        Ok((jdg.context, jdg.statement.s_type))
+    }
+
+    fn resolve_judgement(&self, jdg: &Judgement, infer_sorts: bool) -> Result<Judgement, String> {
+        let mut context: Vec<Statement> = vec![];
+        let mut undeclared: Vec<String> = vec![];
+        let declared = |context: &[Statement], ex: &CCExpression| -> Vec<String> {
+            ex.free_var().into_iter().filter(
+                |x| !context.iter().any(|st| st.subject.var_str().as_ref() == Some(x))).collect()
+        };
+        for stmt in &jdg.context {
+            let s_type = self.resolve(&stmt.s_type)?;
+            undeclared.extend(declared(&context, &s_type));
+            context.push(Statement { subject: stmt.subject.clone(), s_type });
+        }
+        let s_type = self.resolve(&jdg.statement.s_type)?;
+        undeclared.extend(declared(&context, &s_type));
+        let subject = if jdg.statement.subject == CCExpression::Var("?".to_string()) {
+            jdg.statement.subject.clone()
+        } else {
+            let subject = self.resolve(&jdg.statement.subject)?;
+            undeclared.extend(declared(&context, &subject));
+            subject
+        };
+        let inferred = sort_context(&undeclared);
+        if !inferred.is_empty() && !infer_sorts {
+            return Err(format!("undeclared variables: {} (use --infer-sorts to declare them as types)",
+                               inferred.iter().map(|x| x.subject.to_latex()).collect::<Vec<String>>().join(", ")));
+        }
+        Ok(Judgement {
+            defs: vec![],
+            context: [inferred, context].concat(),
+            statement: Statement { subject, s_type }
+        })
+    }
+
+    pub fn verify(&self, expr: &str, infer_sorts: bool) -> Result<Proof, String> {
+        self.verify_judgement(&self.read_judgement(expr, infer_sorts)?)
+    }
+
+    pub fn read_judgement(&self, expr: &str, infer_sorts: bool) -> Result<Judgement, String> {
+        let jdg = parse_judgement(expr).ok_or(format!("failed to parse: ({})", expr))?;
+        self.resolve_judgement(&jdg, infer_sorts)
+    }
+
+    pub fn verify_judgement(&self, jdg: &Judgement) -> Result<Proof, String> {
+        let term = &jdg.statement.subject;
+        let lines = unpack_term_as(&self.spec, term, &jdg.statement.s_type, &jdg.context, &self.defs).map_err(
+            |msg| format!("cannot type {}: {}", term.to_latex(), msg))?;
+        let found = &lines.last().unwrap().statement.s_type;
+        if !self.spec.subtype(found, &jdg.statement.s_type) {
+            return Err(format!("{} has type {}, not {}", term.to_latex(),
+                               found.to_latex(), jdg.statement.s_type.to_latex()));
+        }
+        if !found.alpha_equiv(&jdg.statement.s_type) {
+            return Err(format!("{} is not a type", jdg.statement.s_type.to_latex()));
+        }
+        let refs = check_proof_in(&self.spec, &self.defs, &lines)?;
+        let proof = Proof { lines, refs };
+        verify_proof_in(&self.spec, &self.defs, &proof)?;
+        Ok(proof)
+    }
+
+    pub fn filled_text(&self, text: &str) -> String {
+        let mut filled: Vec<(usize, usize, String)> = vec![];
+        for (start, end, block) in block_ranges(text) {
+            if let Some(def) = parse_definition(&block).filter(|d| d.is_hole()) {
+                if let Some(full) = self.get(&def.name) {
+                    filled.push((start, end, full.to_latex().trim().to_string()));
+                }
+            }
+        }
+        let mut output: Vec<String> = vec![];
+        for (idx, line) in text.lines().enumerate() {
+            match filled.iter().find(|(start, end, _)| (*start..=*end).contains(&(idx + 1))) {
+                Some((start, _, latex)) if *start == idx + 1 => output.push(latex.clone()),
+                Some(_) => {},
+                None => output.push(line.to_string())
+            }
+        }
+        output.join("\n") + "\n"
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse, parse_judgement};
+    use crate::find_term::{find_term};
+
+    #[test]
+    fn add_definitions() {
+        let mut env = Environment::blank();
+        let id = parse_definition("A : \\ast \\vartriangleright id \\langle A \\rangle := \\lambda x : A . x : A \\to A").unwrap();
+        let proof = env.add(id.clone()).unwrap();
+        assert_eq!(proof.lines.last().unwrap().to_latex(), "A : \\ast \\vdash \\lambda x : A . x : A \\to A");
+        assert_eq!(proof.lines.len(), proof.refs.len());
+
+        let lem = parse_definition("A : \\ast \\vartriangleright lem \\langle A \\rangle := \\independent : \\neg A \\vee A").unwrap();
+        assert!(env.add(lem).is_ok());
+        let twice = parse_definition("B : \\ast \\vartriangleright twice \\langle B \\rangle := id \\langle B \\to B \\rangle : (B \\to B) \\to B \\to B").unwrap();
+        assert!(env.add(twice).is_ok());
+        assert_eq!(env.defs.len(), 3);
+        assert_eq!(env.derivations.len(), 3);
+
+        assert_eq!(env.add(id).unwrap_err(), "duplicate definition: id");
+    }
+
+    #[test]
+    fn load_theory_text() {
+        let text = "% identity\n\
+                    A : \\ast \\vartriangleright id \\langle A \\rangle :=\n\
+                    \\lambda x : A . x : A \\to A\n\
+                    \n\
+                    \\vartriangleright bot \\langle \\rangle := \\prod A : \\ast . A : \\ast % falsity\n\
+                    A : \\ast \\vartriangleright lem \\langle A \\rangle := \\independent : \\neg A \\vee A\n";
+        assert_eq!(theory_blocks(text).iter().map(|x| x.0).collect::<Vec<usize>>(), [2, 5, 6]);
+        let mut env = Environment::blank();
+        env.add_text(text).unwrap();
+        assert_eq!(env.defs.iter().map(|d| d.name.clone()).collect::<Vec<String>>(), ["id", "bot", "lem"]);
+
+        let err = Environment::blank().add_text("\\vartriangleright c \\langle \\rangle := x : \\ast\n\n\\vartriangleright").unwrap_err();
+        assert!(err.starts_with("line 1: c: "));
+        let err = env.add_text("\n\\vartriangleright d := \\ast").unwrap_err();
+        assert_eq!(err, "line 2: failed to parse definition: \\vartriangleright d := \\ast");
+    }
+
+    #[test]
+    fn fill_holes() {
+        let text = "A : \\ast \\vartriangleright id \\langle A \\rangle := ? : A \\to A % found by search\n\
+                    \n\
+                    A : \\ast, B : \\ast \\vartriangleright k \\langle A, B \\rangle := \\lambda a : A . \\lambda b : B . a\n\
+                    : A \\to B \\to A\n";
+        let mut env = Environment::blank();
+        env.add_text(text).unwrap();
+        assert_eq!(env.filled, [0]);
+        assert!(!env.defs[0].is_hole());
+        assert_eq!(env.filled_text(text).lines().collect::<Vec<&str>>(), [
+            "A : \\ast \\vartriangleright id \\langle A \\rangle := \\lambda b : A . b : A \\to A",
+            "",
+            "A : \\ast, B : \\ast \\vartriangleright k \\langle A, B \\rangle := \\lambda a : A . \\lambda b : B . a",
+            ": A \\to B \\to A"]);
+
+        let mut env = Environment { search: SearchOptions { limit: 50, ..SearchOptions::standard() }, ..Environment::blank() };
+        let err = env.add_text("A : \\ast \\vartriangleright bad \\langle A \\rangle := ? : A").unwrap_err();
+        assert!(err.starts_with("line 1: bad: no term found for A: "));
+        assert!(err.contains("expansions: "));
+    }
+
+    #[test]
+    fn goal_judgements() {
+        let env = Environment::blank();
+        let (ctx, s_type) = env.parse_goal("A \\to B", false).unwrap();
+        assert_eq!(Statement::ctx_str(&ctx), "B : \\ast, A : \\ast");
+        assert_eq!(s_type.to_latex(), "A \\to B");
+
+        let goal = "A : \\ast, P : A \\to \\ast, h : \\prod x : A . P x, a : A \\vdash ? : P a";
+        let (ctx, s_type) = env.parse_goal(goal, false).unwrap();
+        assert_eq!(ctx.len(), 4);
+        let proof = find_term(&s_type, &ctx, &env.defs).unwrap();
+        assert_eq!(proof.lines.last().unwrap().to_latex(),
+                   "A : \\ast, P : A \\to \\ast, h : \\prod x : A . P x, a : A \\vdash h a : P a");
+
+        let goal = "h : P \\to Q, p : P \\vdash ? : Q";
+        assert_eq!(env.parse_goal(goal, false).unwrap_err(),
+                   "undeclared variables: Q, P (use --infer-sorts to declare them as types)");
+        let (ctx, _) = env.parse_goal(goal, true).unwrap();
+        assert_eq!(Statement::ctx_str(&ctx), "Q : \\ast, P : \\ast, h : P \\to Q, p : P");
+        assert!(env.parse_goal("A : \\ast \\vdash x : A", false).is_err());
+    }
+
+    #[test]
+    fn verify_judgements() {
+        let mut env = Environment::blank();
+        let proof = env.verify("A : \\ast \\vdash \\lambda x : A . x : A \\to A", false).unwrap();
+        assert_eq!(proof.refs.last().unwrap().rule, "abst");
+        let proof = env.verify("A : \\ast, P : A \\to \\ast, a : A, h : P ((\\lambda y : A . y) a) \\vdash h : P a", false).unwrap();
+        assert_eq!(proof.refs.last().unwrap().rule, "conv");
+
+        assert_eq!(env.verify("A : \\ast, B : \\ast \\vdash \\lambda x : A . x : A \\to B", false).unwrap_err(),
+                   "\\lambda x : A . x has type A \\to A, not A \\to B");
+        assert_eq!(env.verify("\\vdash \\lambda x : A . x : A \\to A", false).unwrap_err(),
+                   "undeclared variables: A (use --infer-sorts to declare them as types)");
+        assert!(env.verify("\\vdash \\lambda x : A . x : A \\to A", true).is_ok());
+        assert!(env.verify("A : \\ast, x : A \\vdash x x : A", false).unwrap_err().starts_with("cannot type x x: "));
+        assert_eq!(env.verify("A : \\ast, x : A \\vdash x : (\\lambda y : A . y y) (\\lambda y : A . y y)", false).unwrap_err(),
+                   "x has type A, not (\\lambda y : A . y y) (\\lambda y : A . y y)");
+
+        assert_eq!(env.verify("A : \\ast, a : A \\vdash a : (\\lambda y : A . A) (a a)", false).unwrap_err(),
+                   "(\\lambda y : A . A) (a a) is not a type");
+
+        env.spec = PtsSpec::ecc();
+        let proof = env.verify("\\vdash \\ast : \\mathrm{Type}_{1}", false).unwrap();
+        assert_eq!(proof.refs.last().unwrap().rule, "conv");
+        assert!(env.verify("A : \\ast \\vdash A : \\mathrm{Type}_{1}", false).is_ok());
+        assert!(env.verify("A : \\ast \\vdash A \\to A : \\mathrm{Type}_{2}", false).is_ok());
+        assert!(env.verify("\\vdash \\mathrm{Type}_{1} : \\ast", false).is_err());
+
+        env.spec = PtsSpec::stlc();
+        let err = env.verify("\\vdash \\lambda A : \\ast . \\lambda x : A . x : \\prod A : \\ast . A \\to A", false).unwrap_err();
+        assert!(err.ends_with("product (\\square, \\ast) not allowed in stlc: \\prod a : \\ast . a \\to a"));
+    }
+
+    fn theory_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("stoch_coc_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, text) in files {
+            std::fs::write(dir.join(file), text).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn import_modules() {
+        let dir = theory_dir("import", &[
+            ("logic.tex", "\\vartriangleright bot \\langle \\rangle := \\prod A : \\ast . A : \\ast\n\
+                           \\vartriangleright top \\langle \\rangle := bot \\langle \\rangle \\to bot \\langle \\rangle : \\ast\n\
+                           A : \\ast \\vartriangleright lem \\langle A \\rangle := \\independent : \\neg A \\vee A\n"),
+            ("other.tex", "\\import{logic}\n\
+                           \\vartriangleright bot \\langle \\rangle := \\prod A : \\ast . A \\to A : \\ast\n\
+                           \\vartriangleright unit \\langle \\rangle := \\independent : top \\langle \\rangle\n"),
+            ("a.tex", "\\import{b}\n"),
+            ("b.tex", "\\import{a}\n")]);
+        let mut env = Environment::blank();
+        env.search_path.push(dir.clone());
+        env.add_text("\\import{other}\n\\import{logic}\n").unwrap();
+        assert_eq!(env.defs.iter().map(|d| d.name.clone()).collect::<Vec<String>>(),
+                   ["logic/bot", "logic/top", "logic/lem", "other/bot", "other/unit"]);
+        assert_eq!(env.get("logic/top").unwrap().body.subject.to_latex(),
+                   "(logic/bot \\langle  \\rangle) \\to logic/bot \\langle  \\rangle");
+        assert_eq!(env.get("other/unit").unwrap().body.s_type.to_latex(), "logic/top \\langle  \\rangle");
+
+        let top = parse("top \\langle \\rangle").unwrap();
+        assert_eq!(env.resolve(&top).unwrap().to_latex(), "logic/top \\langle  \\rangle");
+        let bot = parse("bot \\langle \\rangle").unwrap();
+        assert_eq!(env.resolve(&bot).unwrap_err(), "ambiguous definition name: bot (logic/bot, other/bot)");
+
+        let jdg = parse_judgement("D : \\ast \\vdash x : \\neg \\neg D \\to D").unwrap();
+        let proof = find_term(&jdg.statement.s_type, &jdg.context, &env.defs).unwrap();
+        assert!(proof.lines.last().unwrap().statement.subject.def_names().contains(&"logic/lem".to_string()));
+
+        let err = Environment::blank().add_file(dir.join("a.tex").to_str().unwrap()).unwrap_err();
+        assert!(err.ends_with("import cycle: b -> a -> b"), "{}", err);
+        assert!(env.add_text("\\import{nope}").unwrap_err().ends_with("theory not found: nope"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reject_bad_definitions() {
+        let env = Environment::blank();
+        let wrong_type = parse_definition("A : \\ast, B : \\ast \\vartriangleright k \\langle A, B \\rangle := \\lambda x : A . x : A \\to B").unwrap();
+        assert_eq!(env.check(&wrong_type).unwrap_err(), "k: body has type A \\to A, expected A \\to B");
+        let missing_arg = parse_definition("A : \\ast \\vartriangleright m \\langle C \\rangle := \\lambda x : A . x : A \\to A").unwrap();
+        assert_eq!(env.check(&missing_arg).unwrap_err(), "m: argument C is not in the context");
+        let not_type = parse_definition("A : \\ast, a : A \\vartriangleright p \\langle A, a \\rangle := \\independent : a").unwrap();
+        assert!(env.check(&not_type).is_err());
+        let bad_context = parse_definition("a : A \\vartriangleright q \\langle a \\rangle := a : A").unwrap();
+        assert!(env.check(&bad_context).is_err());
+        let omega = parse_definition("A : \\ast, x : A \\vartriangleright bad \\langle A, x \\rangle := x : (\\lambda y : A . y y) (\\lambda y : A . y y)").unwrap();
+        assert!(Environment::blank().add(omega).is_err());
+    }
+}
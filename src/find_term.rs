@@ -1,49 +1,147 @@
 
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration};
 use crate::model::statement::{Statement};
 use crate::model::expression::{CCExpression};
 use crate::model::def::{Definition};
 use crate::model::proof::{Proof};
 use crate::model::partial::{Goal, PartialSol};
-use crate::type_check::{check_proof};
-use crate::unpack_term::{unpack_term};
+use crate::model::pts::{PtsSpec};
+use crate::type_check::{check_proof_in};
+use crate::unpack_term::{unpack_term_in};
 
-use crate::search::proof::{ProofSearchModel};
+use crate::search::proof::{ProofSearchModel, Weights, LemmaMemo, StrategySet};
 use crate::search::control::{SearchControl};
+use crate::search::stats::{SearchStats, SearchOutcome};
 
 
-fn do_search(partial: PartialSol,
-             defs: &[Definition]) -> Result<PartialSol, String> {
+pub struct SearchOptions {
+    pub shortest: bool,
+    pub weights: Weights,
+    pub limit: u32,
+    pub timeout: Option<Duration>,
+    pub frontier: usize,
+    pub trace: bool,
+    pub memo: Option<Rc<RefCell<LemmaMemo>>>,
+    pub strategies: Rc<StrategySet>,
+    pub system: PtsSpec
+}
+
+impl SearchOptions {
+    pub fn standard() -> SearchOptions {
+        SearchOptions {
+            shortest: false,
+            weights: Weights::standard(),
+            limit: 1000000,
+            timeout: None,
+            frontier: 0,
+            trace: false,
+            memo: None,
+            strategies: Rc::new(StrategySet::standard()),
+            system: PtsSpec::coc()
+        }
+    }
+}
 
-    let control = SearchControl {
+fn make_control(defs: &[Definition], opts: &SearchOptions) -> SearchControl<PartialSol> {
+    if let Some(m) = &opts.memo {
+        m.borrow_mut().use_defs(defs);
+    }
+    SearchControl {
         model: Box::new(ProofSearchModel {
-            defs: defs.to_vec()
-        })
-    };
+            memo: opts.memo.clone(),
+            strategies: opts.strategies.clone(),
+            ..ProofSearchModel::new(defs, Box::new(opts.weights.clone()))
+        }),
+        limit: opts.limit,
+        timeout: opts.timeout
+    }
+}
+
+fn initial_partial(s_type: &CCExpression, context: &[Statement]) -> PartialSol {
+    PartialSol {
+        context: context.to_vec(),
+        goals: vec![Goal::Initial(s_type.clone(), vec![])],
+        strat: None
+    }
+}
+
+// a goal that is not a type in the system is rejected before any term is built for it
+fn check_goal(s_type: &CCExpression, context: &[Statement], defs: &[Definition],
+              system: &PtsSpec) -> Result<(), String> {
+    match unpack_term_in(system, s_type, context, defs) {
+        Ok(_) => Ok(()),
+        Err(x) => Err(format!("not typable in {}: {}", system.name, x))
+    }
+}
+
+fn do_search(partial: PartialSol,
+             defs: &[Definition],
+             opts: &SearchOptions) -> (Result<PartialSol, String>, SearchStats) {
+
+    let control = make_control(defs, opts);
 
-    control.search(partial)
+    control.search_stats(partial, opts.shortest, opts.frontier, opts.trace)
 }
 
+pub fn count_expansions(s_type: &CCExpression, context: &[Statement], defs: &[Definition],
+                        opts: &SearchOptions) -> Result<u32, u32> {
+    let control = make_control(defs, opts);
+    match control.search_counted(initial_partial(s_type, context)) {
+        (Ok(_), count) => Ok(count),
+        (Err(_), count) => Err(count)
+    }
+}
+
+#[cfg(test)]
 pub fn find_term(s_type: &CCExpression, context: &[Statement], defs: &[Definition]) -> Result<Proof, String> {
-    let g1 = Goal::Initial(s_type.clone(), vec![]);
-    let partial = PartialSol{
-        context: context.to_vec(),
-        goals: vec![g1],
-    };
-    let res = do_search(partial, defs);
+    find_term_with(s_type, context, defs, &SearchOptions::standard())
+}
 
+#[cfg(test)]
+pub fn find_term_with(s_type: &CCExpression, context: &[Statement], defs: &[Definition],
+                      opts: &SearchOptions) -> Result<Proof, String> {
+    search_term(s_type, context, defs, opts).0
+}
+
+pub fn search_term(s_type: &CCExpression, context: &[Statement], defs: &[Definition],
+                   opts: &SearchOptions) -> (Result<Proof, String>, SearchStats) {
+    if let Err(msg) = check_goal(s_type, context, defs, &opts.system) {
+        return (Err(msg), SearchStats::blank());
+    }
+    let partial = initial_partial(s_type, context);
+    let (res, mut stats) = do_search(partial, defs, opts);
+    let proof = proof_from_search(res, context, defs, &opts.system);
+    if proof.is_err() && stats.outcome == SearchOutcome::Solved {
+        stats.outcome = SearchOutcome::Failed;
+    }
+    (proof, stats)
+}
+
+pub fn search_goal(s_type: &CCExpression, context: &[Statement], defs: &[Definition],
+                   opts: &SearchOptions) -> Result<Goal, String> {
+    check_goal(s_type, context, defs, &opts.system)?;
+    let (res, _) = do_search(initial_partial(s_type, context), defs, opts);
+    Ok(res?.goals.last().unwrap().clone())
+}
+
+pub fn proof_from_search(res: Result<PartialSol, String>, context: &[Statement],
+                     defs: &[Definition], system: &PtsSpec) -> Result<Proof, String> {
     match res {
         Ok(out_partial) => {
             let lines_o = out_partial.goals.last().unwrap();
             if let Goal::Final(lines) = lines_o {
                 let term: &CCExpression = &lines.last().unwrap().statement.subject;
 
-                let full_lines = unpack_term(term, context, defs);
+                let full_lines = unpack_term_in(system, term, context, defs);
                 if full_lines.is_err() {
                     return Err(full_lines.unwrap_err());
                 }
-                let refs_o = check_proof(defs, &full_lines.as_ref().unwrap());
+                let refs_o = check_proof_in(system, defs, full_lines.as_ref().unwrap());
                 match refs_o {
                     Ok(refs) => Ok(Proof { lines: full_lines.as_ref().unwrap().to_vec(), refs: refs }),
+                    Err(x) if system.name != "coc" => Err(format!("not typable in {}: {}", system.name, x)),
                     Err(x) => {
                         eprintln!("lines failed check:\n{}", full_lines.as_ref().unwrap().iter().map(|x| x.to_latex()).collect::<Vec<String>>().join("\n"));
                         eprintln!("err: {}", x);
@@ -142,4 +240,95 @@ mod tests {
             "\\lambda b : \\neg \\neg D . lem \\langle D \\rangle D (\\lambda c : \\neg D . b c D) (\\lambda c : D . c)"
             );
     }
+
+    #[test]
+    fn find_def_with_proof_argument() {
+        let jdg: Judgement = parse_judgement("P : \\ast \\vdash y : Box \\langle P \\to P \\rangle").unwrap();
+        let defs = vec![
+            parse_definition("A : \\ast \\vartriangleright Box \\langle A \\rangle := \\independent : \\ast").unwrap(),
+            parse_definition("A : \\ast, a : A \\vartriangleright box \\langle A, a \\rangle := \\independent : Box \\langle A \\rangle").unwrap()
+        ];
+        let proof = find_term(&jdg.statement.s_type, &jdg.context, &defs);
+        assert_eq!(proof.unwrap().lines.last().unwrap().to_latex(),
+                   "P : \\ast \\vdash box \\langle P \\to P, \\lambda b : P . b \\rangle : Box \\langle P \\to P \\rangle");
+    }
+
+    #[test]
+    fn find_shortest_double_neg_intro() {
+        let jdg = parse_judgement("A : \\ast \\vdash x : A \\to \\neg \\neg A").unwrap();
+        let t1 = jdg.statement.s_type.clone();
+        let first = find_term(&t1, &jdg.context, &[]).unwrap();
+        let shortest = find_term_with(&t1, &jdg.context, &[],
+                                      &SearchOptions { shortest: true, ..SearchOptions::standard() }).unwrap();
+        let first_term = &first.lines.last().unwrap().statement.subject;
+        let short_term = &shortest.lines.last().unwrap().statement.subject;
+
+        assert_eq!(short_term.to_latex(), "\\lambda b : A . \\lambda a : \\neg A . a b");
+        assert!(short_term.size() < first_term.size());
+    }
+
+    #[test]
+    fn search_stats_reported() {
+        let jdg = parse_judgement("A : \\ast, B : \\ast \\vdash x : (A \\wedge B) \\to A").unwrap();
+        let (proof, stats) = search_term(&jdg.statement.s_type, &jdg.context, &[],
+                                         &SearchOptions::standard());
+        assert!(proof.is_ok());
+        assert_eq!(stats.outcome, SearchOutcome::Solved);
+        assert!(stats.expansions > 0);
+        assert!(stats.strategies["unpack_type_abs"].calls > 0);
+
+        let jdg2 = parse_judgement("A : \\ast, B : \\ast \\vdash x : A \\to B").unwrap();
+        let opts = SearchOptions { frontier: 3, ..SearchOptions::standard() };
+        let (proof2, stats2) = search_term(&jdg2.statement.s_type, &jdg2.context, &[], &opts);
+        assert_eq!(proof2.unwrap_err(), "Exhausted all search options.");
+        assert_eq!(stats2.outcome, SearchOutcome::Exhausted);
+        assert_eq!(stats2.frontier.len(), 0);
+    }
+
+    #[test]
+    fn disabled_strategy_not_used() {
+        let jdg: Judgement = parse_judgement("D:\\ast \\vdash y: \\neg \\neg D \\to D").unwrap();
+        let def = parse_definition("A : \\ast \\vartriangleright lem \\langle A \\rangle := \\independent : \\neg A \\vee A").unwrap();
+        let mut strategies = StrategySet::standard();
+        strategies.configure("-def_known").unwrap();
+        let opts = SearchOptions {
+            limit: 2000,
+            strategies: Rc::new(strategies),
+            ..SearchOptions::standard()
+        };
+        let (proof, stats) = search_term(&jdg.statement.s_type, &jdg.context, &[def], &opts);
+        assert!(proof.is_err());
+        assert!(!stats.strategies.contains_key("def_known"));
+    }
+
+    #[test]
+    fn search_in_system() {
+        let jdg = parse_judgement("\\vdash x : \\prod A : \\ast . A \\to A").unwrap();
+        let stlc = SearchOptions { system: PtsSpec::stlc(), ..SearchOptions::standard() };
+        let l2 = SearchOptions { system: PtsSpec::lambda2(), ..SearchOptions::standard() };
+        let err = find_term_with(&jdg.statement.s_type, &jdg.context, &[], &stlc).unwrap_err();
+        assert!(err.starts_with("not typable in stlc"));
+        let (_, stats) = search_term(&jdg.statement.s_type, &jdg.context, &[], &stlc);
+        assert_eq!(stats.expansions, 0);
+        assert_eq!(stats.outcome, SearchOutcome::Failed);
+        assert!(find_term_with(&jdg.statement.s_type, &jdg.context, &[], &l2).is_ok());
+    }
+
+    #[test]
+    fn memo_shared_between_goals() {
+        let jdg = parse_judgement("A : \\ast, B : \\ast, C : \\ast \\vdash x : (A \\to B) \\to (B \\to C) \\to A \\to C").unwrap();
+        let memo = Rc::new(RefCell::new(LemmaMemo::blank()));
+        let opts = SearchOptions { memo: Some(memo.clone()), ..SearchOptions::standard() };
+        let (plain, plain_stats) = search_term(&jdg.statement.s_type, &jdg.context, &[],
+                                               &SearchOptions::standard());
+        let (first, first_stats) = search_term(&jdg.statement.s_type, &jdg.context, &[], &opts);
+        assert_eq!(first.unwrap().lines.last().unwrap().statement.s_type,
+                   plain.unwrap().lines.last().unwrap().statement.s_type);
+        assert!(first_stats.expansions <= plain_stats.expansions);
+
+        let (second, second_stats) = search_term(&jdg.statement.s_type, &jdg.context, &[], &opts);
+        assert!(second.is_ok());
+        assert_eq!(second_stats.expansions, 1);
+        assert!(memo.borrow().hits > 0);
+    }
 }
@@ -4,51 +4,170 @@ mod model;
 mod parser;
 mod type_check;
 mod unpack_term;
+mod environment;
+mod stdlib;
 mod find_term;
 mod search;
 mod serial;
+mod tune;
+mod batch;
+mod repl;
+mod tactic;
+mod normalize;
 
 use crate::parser::{parse};
 use crate::model::proof::{Proof};
-use crate::model::expression::{CCExpression};
-use crate::model::statement::{Statement};
-use crate::serial::{flagderiv_output};
-use crate::find_term::{find_term};
-use argparse::{ArgumentParser, StoreTrue, Store};
-
-
-fn remove_dup<T: PartialEq+Clone>(lst: Vec<T>) -> Vec<T> {
-    let mut output: Vec<T> = vec![];
-    for item in lst {
-        if !output.contains(&item) {
-            output.push(item.clone());
+use crate::model::def::{Definition};
+use crate::environment::{Environment, sort_context};
+use crate::model::pts::{PtsSpec};
+use crate::serial::{flagderiv_output, bussproofs_output, fitch_output, logicproof_output, result_json, ErrorKind};
+use crate::find_term::{search_term, SearchOptions};
+use crate::search::stats::{SearchStats};
+use crate::search::proof::{Weights, LemmaMemo, StrategySet};
+use crate::tune::{TuneGoal, tune_weights};
+use crate::repl::{Repl};
+use crate::normalize::{normalize_input};
+use crate::batch::{batch_goals, run_batch, batch_document, summary_table, exit_code};
+use argparse::{ArgumentParser, StoreTrue, Store, Collect};
+use std::path::{PathBuf};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+
+fn find_proof(expr: &str, env: &Environment, infer_sorts: bool) -> (Result<Proof, String>, Option<SearchStats>) {
+    match env.parse_goal(expr, infer_sorts) {
+        Ok((ctx, t1)) => {
+            let (res, stats) = search_term(&t1, &ctx, &env.defs, &env.search);
+            (res, Some(stats))
+        },
+        Err(msg) => (Err(msg), None)
+    }
+}
+
+fn read_goals(path: &str) -> Result<Vec<TuneGoal>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("failed to read {}: {}", path, e)); }
+    };
+    let mut output: Vec<TuneGoal> = vec![];
+    for line in text.lines() {
+        let content = line.split('%').next().unwrap().trim();
+        if content.is_empty() {
+            continue;
+        }
+        match parse(content) {
+            Some(t1) => {
+                let ctx = sort_context(&t1.free_var());
+                output.push(TuneGoal { s_type: t1, context: ctx });
+            },
+            None => { return Err(format!("failed to parse: ({})", content)); }
         }
     }
-    return output;
+    Ok(output)
 }
 
-fn make_fake_context(ex: &CCExpression) -> Vec<Statement> {
-    remove_dup(ex.free_var().iter().map(
-        |x|
-        Statement {
-            subject: CCExpression::Var(x.to_string()),
-            s_type: CCExpression::Star
-        }).collect())
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
 }
 
-fn find_proof(expr: &str) -> Result<Proof, String> {
-    let t0 = parse(expr);
-    match t0 {
-        Some(t1) => {
-            let ctx = make_fake_context(&t1);
-            find_term(&t1, &ctx, &[])
+// with --format json the error is printed as a result object of the given mode
+fn fail_as(json: Option<&str>, kind: ErrorKind, msg: &str) -> ! {
+    if let Some(mode) = json {
+        println!("{}", result_json(mode, &Err((kind, msg.to_string())), None, &Duration::ZERO));
+        std::process::exit(1);
+    }
+    fail(msg)
+}
+
+fn run_tune(corpus_path: &str, defs: &[Definition], start: &Weights) {
+    match read_goals(corpus_path) {
+        Ok(corpus) => {
+            let (weights, cost) = tune_weights(&corpus, defs, start, 20000);
+            println!("% expansions on corpus: {}", cost);
+            println!("{}", weights.to_text());
         },
-        None => Err(format!("failed to parse: ({})", expr))
+        Err(msg) => fail(&msg)
+    }
+}
+
+fn run_batch_file(path: &str, output_path: &str, env: &Environment, infer_sorts: bool, flagderiv: bool) -> i32 {
+    let text = match std::fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) => { eprintln!("failed to read {}: {}", path, e); return 1; }
+    };
+    let results = run_batch(&batch_goals(&text), env, infer_sorts);
+    let doc = match batch_document(&results, flagderiv) {
+        Ok(x) => x,
+        Err(msg) => { eprintln!("{}", msg); return 1; }
+    };
+    if !output_path.is_empty() {
+        if let Err(e) = std::fs::write(output_path, doc) {
+            eprintln!("failed to write {}: {}", output_path, e);
+            return 1;
+        }
+    } else {
+        print!("{}", doc);
+    }
+    eprintln!("{}", summary_table(&results));
+    exit_code(&results)
+}
+
+fn write_filled(out_path: &str, theory_path: &str, env: &Environment, flagderiv: bool) -> Result<(), String> {
+    let output = if flagderiv {
+        let mut proofs: Vec<String> = vec![];
+        for idx in &env.filled {
+            proofs.push(format!("% {}\n{}", env.defs[*idx].name, flagderiv_output(&env.derivations[*idx])?));
+        }
+        proofs.join("\n\n") + "\n"
+    } else {
+        match std::fs::read_to_string(theory_path) {
+            Ok(text) => env.filled_text(&text),
+            Err(e) => { return Err(format!("failed to read theory ({}): {}", theory_path, e)); }
+        }
+    };
+    std::fs::write(out_path, output).map_err(|e| format!("failed to write {}: {}", out_path, e))
+}
+
+fn typeset(proof: &Proof, env: &Environment, style: &str) -> Result<String, String> {
+    match style {
+        "bussproofs" => bussproofs_output(proof, &env.spec, &env.defs),
+        "fitch" => fitch_output(proof),
+        "logicproof" => logicproof_output(proof),
+        "flagderiv" => flagderiv_output(proof),
+        _ => Ok(proof.to_latex())
     }
 }
 
 fn main() {
     let mut flagderiv: bool = false;
+    let mut bussproofs: bool = false;
+    let mut fitch: bool = false;
+    let mut logicproof: bool = false;
+    let mut shortest: bool = false;
+    let mut memo: bool = false;
+    let mut strategies_spec = "".to_string();
+    let mut system = "coc".to_string();
+    let mut stats: bool = false;
+    let mut stats_json: bool = false;
+    let mut frontier: usize = 5;
+    let mut dot_path = "".to_string();
+    let mut weights_path = "".to_string();
+    let mut theory_path = "".to_string();
+    let mut theory_dirs: Vec<String> = vec![];
+    let mut fill_path = "".to_string();
+    let mut infer_sorts: bool = false;
+    let mut imports: Vec<String> = vec![];
+    let mut tune_path = "".to_string();
+    let mut batch_path = "".to_string();
+    let mut output_path = "".to_string();
+    let mut timeout: u64 = 0;
+    let mut repl: bool = false;
+    let mut check: bool = false;
+    let mut normalize: bool = false;
+    let mut steps: bool = false;
+    let mut format = "latex".to_string();
     let mut expr = "".to_string();
     {
         let mut ap = ArgumentParser::new();
@@ -56,28 +175,235 @@ fn main() {
         ap.refer(&mut flagderiv)
             .add_option(&["--flagderiv"], StoreTrue,
             "Use flagderiv for proof typesetting");
+        ap.refer(&mut bussproofs)
+            .add_option(&["--bussproofs"], StoreTrue,
+            "Typeset the proof as bussproofs derivation trees");
+        ap.refer(&mut fitch)
+            .add_option(&["--fitch"], StoreTrue,
+            "Typeset the proof for the fitch package");
+        ap.refer(&mut logicproof)
+            .add_option(&["--logicproof"], StoreTrue,
+            "Typeset the proof for the logicproof package");
+        ap.refer(&mut shortest)
+            .add_option(&["--shortest"], StoreTrue,
+            "Search for the smallest term (slower)");
+        ap.refer(&mut memo)
+            .add_option(&["--memo"], StoreTrue,
+            "Cache solved and failed sub-goals during the search");
+        ap.refer(&mut strategies_spec)
+            .add_option(&["--strategies"], Store,
+            "Comma separated strategy changes: -name disables, +name enables, name=N sets the priority");
+        ap.refer(&mut system)
+            .add_option(&["--system"], Store,
+            "Type system the proof is checked in: stlc, l2, lp, lw, coc or ecc (default coc)");
+        ap.refer(&mut infer_sorts)
+            .add_option(&["--infer-sorts"], StoreTrue,
+            "Declare free variables missing from a goal judgement's context as types (A : \\ast); goals without \\vdash always declare them");
+        ap.refer(&mut stats)
+            .add_option(&["--stats"], StoreTrue,
+            "Print search statistics to stderr");
+        ap.refer(&mut stats_json)
+            .add_option(&["--stats-json"], StoreTrue,
+            "Print search statistics to stderr as JSON");
+        ap.refer(&mut frontier)
+            .add_option(&["--frontier"], Store,
+            "Number of best unexplored states included in the statistics (default 5)");
+        ap.refer(&mut dot_path)
+            .add_option(&["--dot"], Store,
+            "Write the explored search tree to a Graphviz DOT file");
+        ap.refer(&mut theory_path)
+            .add_option(&["--theory"], Store,
+            "Load and check definitions from a theory file before searching");
+        ap.refer(&mut theory_dirs)
+            .add_option(&["--theory-path"], Collect,
+            "Directory searched for imported theory files (can be repeated)");
+        ap.refer(&mut fill_path)
+            .add_option(&["--fill"], Store,
+            "Write the theory with every ? body found by search to this file (flagderiv proofs with --flagderiv)");
+        ap.refer(&mut imports)
+            .add_option(&["--import"], Collect,
+            "Import a theory module by name, such as the bundled std/logic (can be repeated)");
+        ap.refer(&mut weights_path)
+            .add_option(&["--weights"], Store,
+            "Load search heuristic weights from a file");
+        ap.refer(&mut tune_path)
+            .add_option(&["--tune"], Store,
+            "Fit heuristic weights on a file of goals (one per line) and print them");
+        ap.refer(&mut batch_path)
+            .add_option(&["--batch"], Store,
+            "Search for every goal in a file (one per line) and print a summary table");
+        ap.refer(&mut output_path)
+            .add_option(&["--output"], Store,
+            "Write the results of --batch to this file instead of stdout");
+        ap.refer(&mut timeout)
+            .add_option(&["--timeout"], Store,
+            "Give up on a goal after this many milliseconds (0 for no timeout)");
+        ap.refer(&mut repl)
+            .add_option(&["--repl"], StoreTrue,
+            "Start an interactive session (:help lists the commands)");
+        ap.refer(&mut check)
+            .add_option(&["--check"], StoreTrue,
+            "Type-check the judgement given as expr and print its derivation");
+        ap.refer(&mut normalize)
+            .add_option(&["--normalize"], StoreTrue,
+            "Print the normal form of the term or judgement given as expr, unfolding loaded definitions");
+        ap.refer(&mut steps)
+            .add_option(&["--steps"], StoreTrue,
+            "With --normalize, print every reduction step as an align* chain");
+        ap.refer(&mut format)
+            .add_option(&["--format"], Store,
+            "Output format of a search or --check: latex or json (default latex)");
         ap.refer(&mut expr)
             .add_argument("expr", Store,
-            "Find a term for this type").required();
+            "Find a term for this type");
         ap.parse_args_or_exit();
     }
 
-    let proof_r = find_proof(&expr);
+    // any format other than latex asks for a machine readable answer, so an unknown one is reported as json
+    let json = if format == "latex" { None } else if check { Some("check") } else { Some("search") };
+    if json.is_some() && format != "json" {
+        fail_as(json, ErrorKind::Usage, &format!("unknown format: {} (known: latex, json)", format));
+    }
+
+    let weights = if !weights_path.is_empty() {
+        match Weights::load(&weights_path) {
+            Ok(w) => w,
+            Err(msg) => fail_as(json, ErrorKind::Usage, &msg)
+        }
+    } else {
+        Weights::standard()
+    };
+
+    let mut strategies = StrategySet::standard();
+    if let Err(msg) = strategies.configure(&strategies_spec) {
+        fail_as(json, ErrorKind::Usage, &msg);
+    }
+
+    let spec = match PtsSpec::preset(&system) {
+        Ok(x) => x,
+        Err(msg) => fail_as(json, ErrorKind::Usage, &msg)
+    };
+
+    let opts = SearchOptions {
+        shortest,
+        weights: weights.clone(),
+        frontier: if stats || stats_json { frontier } else { 0 },
+        trace: !dot_path.is_empty(),
+        strategies: Rc::new(strategies),
+        memo: if memo { Some(Rc::new(RefCell::new(LemmaMemo::blank()))) } else { None },
+        system: spec.clone(),
+        timeout: if timeout > 0 { Some(Duration::from_millis(timeout)) } else { None },
+        ..SearchOptions::standard()
+    };
+
+    let mut env = Environment {
+        spec,
+        search: opts,
+        search_path: theory_dirs.iter().map(PathBuf::from).collect(),
+        ..Environment::blank()
+    };
+    for name in &imports {
+        if let Err(msg) = env.import(name, None) {
+            fail_as(json, ErrorKind::Theory, &msg);
+        }
+    }
+    if !theory_path.is_empty() {
+        if let Err(msg) = env.add_file(&theory_path) {
+            fail_as(json, ErrorKind::Theory, &msg);
+        }
+    }
+
+    if !fill_path.is_empty() {
+        if let Err(msg) = write_filled(&fill_path, &theory_path, &env, flagderiv) {
+            fail(&msg);
+        }
+        return;
+    }
+    if repl {
+        Repl::new(env).run(std::io::stdin().lock(), std::io::stdout());
+        return;
+    }
+    if !batch_path.is_empty() {
+        std::process::exit(run_batch_file(&batch_path, &output_path, &env, infer_sorts, flagderiv));
+    }
+    if !tune_path.is_empty() {
+        run_tune(&tune_path, &env.defs, &weights);
+        return;
+    }
+    if expr.is_empty() {
+        fail_as(json, ErrorKind::Usage, "no expression given");
+    }
+
+    if normalize {
+        match normalize_input(&expr, &env, steps) {
+            Ok(text) => { println!("{}", text); },
+            Err(msg) => fail(&msg)
+        }
+        return;
+    }
+    if check && json.is_some() {
+        let t0 = Instant::now();
+        let res = env.read_judgement(&expr, infer_sorts).map_err(|msg| (ErrorKind::Goal, msg)).and_then(
+            |jdg| env.verify_judgement(&jdg).map_err(|msg| (ErrorKind::Type, msg)));
+        println!("{}", result_json("check", &res, None, &t0.elapsed()));
+        std::process::exit(if res.is_ok() { 0 } else { 1 });
+    }
+    let style = if bussproofs {
+        "bussproofs"
+    } else if fitch {
+        "fitch"
+    } else if logicproof {
+        "logicproof"
+    } else if flagderiv {
+        "flagderiv"
+    } else {
+        ""
+    };
+    if check {
+        let output = env.verify(&expr, infer_sorts).and_then(|proof| typeset(&proof, &env, style));
+        match output {
+            Ok(p_str) => { println!("{}", p_str); },
+            Err(msg) => fail(&msg)
+        }
+        return;
+    }
+
+    let t0 = Instant::now();
+    let (proof_r, search_stats) = find_proof(&expr, &env, infer_sorts);
+    let elapsed = t0.elapsed();
+
+    if let Some(st) = &search_stats {
+        if stats {
+            eprintln!("{}", st.to_report());
+        }
+        if stats_json {
+            eprintln!("{}", st.to_json());
+        }
+        if let Some(trace) = &st.trace {
+            if let Err(e) = std::fs::write(&dot_path, trace.to_dot() + "\n") {
+                eprintln!("failed to write {}: {}", dot_path, e);
+            }
+        }
+    }
+
+    if json.is_some() {
+        let kind = search_stats.as_ref().map_or(ErrorKind::Goal, |st| ErrorKind::Search(st.outcome));
+        let res = proof_r.map_err(|msg| (kind, msg));
+        println!("{}", result_json("search", &res, search_stats.as_ref(), &elapsed));
+        std::process::exit(if res.is_ok() { 0 } else { 1 });
+    }
 
     match proof_r {
         Ok(proof) => {
-            if !flagderiv {
+            if style.is_empty() {
                 println!("{}", proof.lines.last().unwrap().to_latex());
             } else {
-                let fd_output = flagderiv_output(&proof);
-                match fd_output {
+                match typeset(&proof, &env, style) {
                     Ok(p_str) => { println!("{}", p_str); },
-                    Err(msg) => { eprintln!("{}", msg); },
+                    Err(msg) => fail(&msg)
                 }
             }
         },
-        Err(msg) => {
-            eprintln!("{}", msg);
-        }
+        Err(msg) => fail(&msg)
     }
 }
@@ -5,3 +5,4 @@ pub mod rules;
 pub mod proof;
 pub mod partial;
 pub mod def;
+pub mod pts;
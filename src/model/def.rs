@@ -24,14 +24,31 @@ impl Definition {
         );
     }
 
-    pub fn arg_statements(&self) -> Vec<Statement> {
-        self.args.iter().map(
-            |x| Statement {
+    pub fn is_hole(&self) -> bool {
+        self.body.subject == CCExpression::Var("?".to_string())
+    }
+
+    pub fn unfold(&self, args: &[CCExpression]) -> Option<CCExpression> {
+        if self.body.subject == CCExpression::Prim || self.is_hole() || args.len() != self.args.len() {
+            return None;
+        }
+        let mut output = self.body.subject.clone();
+        for (idx, arg) in self.args.iter().enumerate() {
+            output = output.instantiate(arg, &CCExpression::Var(format!("arg_{{{}}}", idx)));
+        }
+        for (idx, arg) in args.iter().enumerate() {
+            output = output.instantiate(&format!("arg_{{{}}}", idx), arg);
+        }
+        Some(output)
+    }
+
+    pub fn arg_statements(&self) -> Option<Vec<Statement>> {
+        let types = self.type_list()?;
+        Some(self.args.iter().zip(types).map(
+            |(x, t)| Statement {
                 subject: CCExpression::Var(x.to_string()),
-                s_type: self.context.iter().filter(
-                    |stmt| stmt.subject.var_str() == Some(x.to_string())
-                    ).map(|stmt| stmt.s_type.clone()).next().unwrap()
-            }).collect()
+                s_type: t
+            }).collect())
     }
 
     pub fn type_list(&self) -> Option<Vec<CCExpression>> {
@@ -69,9 +86,11 @@ mod tests {
 
         assert_eq!(def1.to_latex(),
                    "x : A \\vartriangleright ex \\langle x \\rangle := x : A");
-        assert_eq!(def1.arg_statements().iter().map(
+        assert_eq!(def1.arg_statements().unwrap().iter().map(
                 |x| x.to_latex()
                 ).collect::<Vec<String>>(), ["x : A"]);
+        let def2 = Definition { args: vec!["y".to_string()], ..def1 };
+        assert_eq!(def2.arg_statements(), None);
     }
 }
 
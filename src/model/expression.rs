@@ -1,9 +1,14 @@
+use std::collections::HashMap;
+
+const BETA_LIMIT: u32 = 1000;
+
 
 #[derive(Debug,PartialEq,Eq)]
 pub enum CCExpression {
     Var(String),
     Sq,
     Star,
+    Universe(u32),
     Prim,
     Def(String, Vec<CCExpression>),
     Application(Box<CCExpression>, Box<CCExpression>),
@@ -54,6 +59,14 @@ fn type_abs_to_latex(ex: &CCExpression, arg: &String, t: &CCExpression,
 }
 
 
+pub fn fresh_name(base: &str, used: &[String]) -> String {
+    let mut name = format!("{}'", base);
+    while used.contains(&name) {
+        name.push('\'');
+    }
+    name
+}
+
 impl CCExpression {
 
     pub fn to_latex(&self) -> String {
@@ -61,6 +74,7 @@ impl CCExpression {
             CCExpression::Var(x) => x.to_string(),
             CCExpression::Sq => String::from("\\square"),
             CCExpression::Star => String::from("\\ast"),
+            CCExpression::Universe(i) => format!("\\mathrm{{Type}}_{{{}}}", i),
             CCExpression::Prim => String::from("\\independent"),
             CCExpression::Def(name, args) => {
                 let arg_list = args.iter().filter_map(
@@ -223,6 +237,7 @@ impl CCExpression {
             CCExpression::Var(_) => true,
             CCExpression::Star => true,
             CCExpression::Sq => true,
+            CCExpression::Universe(_) => true,
             CCExpression::Def(_, _) => true,
             _ => {
                 if self.is_contradiction() {
@@ -261,6 +276,9 @@ impl CCExpression {
             CCExpression::Application(left, right) => {
                 [left.free_var(), right.free_var()].concat()
             }
+            CCExpression::Def(_, args) => {
+                args.iter().flat_map(|x| x.free_var()).collect()
+            }
             CCExpression::Abs(arg, t, ret) => {
                 ret.free_var().iter().chain(&t.free_var()).filter(
                     |x| *x != arg
@@ -276,6 +294,17 @@ impl CCExpression {
 
     }
 
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            CCExpression::Var(x) => vec![x.clone()],
+            CCExpression::Def(_, args) => args.iter().flat_map(|x| x.names()).collect(),
+            CCExpression::Application(lhs, rhs) => [lhs.names(), rhs.names()].concat(),
+            CCExpression::Abs(arg, a_type, ret) => [vec![arg.clone()], a_type.names(), ret.names()].concat(),
+            CCExpression::TypeAbs(arg, a_type, ret) => [vec![arg.clone()], a_type.names(), ret.names()].concat(),
+            _ => vec![]
+        }
+    }
+
     pub fn is_var(&self) -> bool {
         match self {
             CCExpression::Var(_) => true,
@@ -287,6 +316,7 @@ impl CCExpression {
         match self {
             CCExpression::Star => true,
             CCExpression::Sq => true,
+            CCExpression::Universe(_) => true,
             _ => false,
         }
     }
@@ -295,6 +325,7 @@ impl CCExpression {
         match self {
             CCExpression::Star => CCExpression::Star,
             CCExpression::Sq => CCExpression::Sq,
+            CCExpression::Universe(i) => CCExpression::Universe(*i),
             CCExpression::Prim => CCExpression::Prim,
             CCExpression::Def(name, args) => {
                 CCExpression::Def(
@@ -340,10 +371,50 @@ impl CCExpression {
         }
     }
 
+    pub fn instantiate(&self, token: &str, expr: &CCExpression) -> CCExpression {
+        self.instantiate_all(&[(token.to_string(), expr.clone())])
+    }
+
+    // simultaneous capture-avoiding substitution
+    pub fn instantiate_all(&self, subs: &[(String, CCExpression)]) -> CCExpression {
+        match self {
+            CCExpression::Var(x) => subs.iter().find(|(token, _)| token == x).map_or(
+                self.clone(), |(_, expr)| expr.clone()),
+            CCExpression::Def(name, args) => CCExpression::Def(
+                name.clone(),
+                args.iter().map(|x| x.instantiate_all(subs)).collect()),
+            CCExpression::Application(lhs, rhs) => CCExpression::Application(
+                Box::new(lhs.instantiate_all(subs)),
+                Box::new(rhs.instantiate_all(subs))),
+            CCExpression::Abs(x, a_type, ret) | CCExpression::TypeAbs(x, a_type, ret) => {
+                let inner: Vec<(String, CCExpression)> = subs.iter().filter(
+                    |(token, _)| token != x).cloned().collect();
+                let free = ret.free_var();
+                let mut arg = x.clone();
+                let mut new_ret = *ret.clone();
+                if inner.iter().any(|(token, expr)| free.contains(token) && expr.free_var().contains(x)) {
+                    let used: Vec<String> = inner.iter().flat_map(
+                        |(token, expr)| [vec![token.clone()], expr.names()].concat()).collect();
+                    arg = fresh_name(x, &[ret.names(), used].concat());
+                    new_ret = ret.substitute(x, &CCExpression::Var(arg.clone()));
+                }
+                let new_ret = Box::new(new_ret.instantiate_all(&inner));
+                let new_type = Box::new(a_type.instantiate_all(subs));
+                if let CCExpression::Abs(..) = self {
+                    CCExpression::Abs(arg, new_type, new_ret)
+                } else {
+                    CCExpression::TypeAbs(arg, new_type, new_ret)
+                }
+            },
+            other => other.clone()
+        }
+    }
+
     pub fn alpha_equiv(&self, rhs: &CCExpression) -> bool {
         match (self, rhs) {
             (CCExpression::Star, CCExpression::Star) => true,
             (CCExpression::Sq, CCExpression::Sq) => true,
+            (CCExpression::Universe(i), CCExpression::Universe(j)) => i == j,
             (CCExpression::Prim, CCExpression::Prim) => true,
             (CCExpression::Def(lname, largs), CCExpression::Def(rname, rargs)) => {
                 let names_match: bool = lname == rname;
@@ -361,9 +432,9 @@ impl CCExpression {
                 if x == y {
                     return a_type1.alpha_equiv(a_type2) && ret1.alpha_equiv(ret2);
                 } else {
-                    let new_type = a_type2.substitute(y, &CCExpression::Var(x.clone()));
-                    let new_ret = ret2.substitute(y, &CCExpression::Var(x.clone()));
-                    return a_type1.alpha_equiv(&new_type) && ret1.alpha_equiv(&new_ret);
+                    let fresh = CCExpression::Var(fresh_name(x, &[self.names(), rhs.names()].concat()));
+                    a_type1.alpha_equiv(a_type2)
+                        && ret1.substitute(x, &fresh).alpha_equiv(&ret2.substitute(y, &fresh))
                 }
             },
             (CCExpression::TypeAbs(x, a_type1, ret1),
@@ -371,39 +442,127 @@ impl CCExpression {
                 if x == y {
                     return a_type1.alpha_equiv(a_type2) && ret1.alpha_equiv(ret2);
                 } else {
-                    let new_type = a_type2.substitute(y, &CCExpression::Var(x.clone()));
-                    let new_ret = ret2.substitute(y, &CCExpression::Var(x.clone()));
-                    return a_type1.alpha_equiv(&new_type) && ret1.alpha_equiv(&new_ret);
+                    let fresh = CCExpression::Var(fresh_name(x, &[self.names(), rhs.names()].concat()));
+                    a_type1.alpha_equiv(a_type2)
+                        && ret1.substitute(x, &fresh).alpha_equiv(&ret2.substitute(y, &fresh))
                 }
             },
             (_, _) => false
         }
     }
 
+    // stops contracting after BETA_LIMIT steps, so terms without a
+    // normal form come back partly reduced instead of looping
     pub fn beta_reduce(&self) -> CCExpression {
+        let mut budget = BETA_LIMIT;
+        self.beta_reduce_within(&mut budget)
+    }
+
+    fn beta_reduce_within(&self, budget: &mut u32) -> CCExpression {
         match self {
             CCExpression::Star => CCExpression::Star,
             CCExpression::Sq => CCExpression::Sq,
+            CCExpression::Universe(i) => CCExpression::Universe(*i),
             CCExpression::Prim => CCExpression::Prim,
             CCExpression::Var(x) => CCExpression::Var(x.clone()),
             CCExpression::Application(lhs, rhs) => {
-                let l = lhs.beta_reduce();
-                let r = rhs.beta_reduce();
-                if let CCExpression::Abs(arg, _, ret) = l {
-                    return ret.substitute(&arg, &r);
+                let l = lhs.beta_reduce_within(budget);
+                let r = rhs.beta_reduce_within(budget);
+                match l {
+                    CCExpression::Abs(arg, _, ret) if *budget > 0 => {
+                        *budget -= 1;
+                        ret.instantiate(&arg, &r).beta_reduce_within(budget)
+                    },
+                    _ => CCExpression::Application(Box::new(l), Box::new(r))
                 }
-                return CCExpression::Application(
-                    Box::new(l),
-                    Box::new(r)
-                    );
             },
-            other => other.clone()
+            CCExpression::Abs(arg, a_type, ret) => CCExpression::Abs(
+                arg.clone(),
+                Box::new(a_type.beta_reduce_within(budget)),
+                Box::new(ret.beta_reduce_within(budget))),
+            CCExpression::TypeAbs(arg, a_type, ret) => CCExpression::TypeAbs(
+                arg.clone(),
+                Box::new(a_type.beta_reduce_within(budget)),
+                Box::new(ret.beta_reduce_within(budget))),
+            CCExpression::Def(name, args) => CCExpression::Def(
+                name.clone(),
+                args.iter().map(|x| x.beta_reduce_within(budget)).collect())
         }
     }
 
     pub fn beta_equiv(&self, rhs: &CCExpression) -> bool {
         return self.beta_reduce().alpha_equiv(&rhs.beta_reduce());
     }
+
+    fn canonical(&self, depth: u32) -> CCExpression {
+        match self {
+            CCExpression::Application(lhs, rhs) => CCExpression::Application(
+                Box::new(lhs.canonical(depth)),
+                Box::new(rhs.canonical(depth))),
+            CCExpression::Def(name, args) => CCExpression::Def(
+                name.clone(),
+                args.iter().map(|x| x.canonical(depth)).collect()),
+            CCExpression::Abs(arg, a_type, ret) => {
+                let new_arg = format!("bound_{{{}}}", depth);
+                CCExpression::Abs(new_arg.clone(),
+                    Box::new(a_type.canonical(depth + 1)),
+                    Box::new(ret.substitute(arg, &CCExpression::Var(new_arg)).canonical(depth + 1)))
+            },
+            CCExpression::TypeAbs(arg, a_type, ret) => {
+                let new_arg = format!("bound_{{{}}}", depth);
+                CCExpression::TypeAbs(new_arg.clone(),
+                    Box::new(a_type.canonical(depth + 1)),
+                    Box::new(ret.substitute(arg, &CCExpression::Var(new_arg)).canonical(depth + 1)))
+            },
+            other => other.clone()
+        }
+    }
+
+    pub fn canonical_key(&self) -> String {
+        self.canonical(0).to_latex()
+    }
+
+    pub fn def_names(&self) -> Vec<String> {
+        match self {
+            CCExpression::Def(name, args) => {
+                [vec![name.clone()], args.iter().flat_map(|x| x.def_names()).collect()].concat()
+            },
+            CCExpression::Application(lhs, rhs) => [lhs.def_names(), rhs.def_names()].concat(),
+            CCExpression::Abs(_, a_type, ret) => [a_type.def_names(), ret.def_names()].concat(),
+            CCExpression::TypeAbs(_, a_type, ret) => [a_type.def_names(), ret.def_names()].concat(),
+            _ => vec![]
+        }
+    }
+
+    pub fn rename_defs(&self, names: &HashMap<String, String>) -> CCExpression {
+        match self {
+            CCExpression::Def(name, args) => CCExpression::Def(
+                names.get(name).unwrap_or(name).clone(),
+                args.iter().map(|x| x.rename_defs(names)).collect()),
+            CCExpression::Application(lhs, rhs) => CCExpression::Application(
+                Box::new(lhs.rename_defs(names)),
+                Box::new(rhs.rename_defs(names))),
+            CCExpression::Abs(arg, a_type, ret) => CCExpression::Abs(arg.clone(),
+                Box::new(a_type.rename_defs(names)),
+                Box::new(ret.rename_defs(names))),
+            CCExpression::TypeAbs(arg, a_type, ret) => CCExpression::TypeAbs(arg.clone(),
+                Box::new(a_type.rename_defs(names)),
+                Box::new(ret.rename_defs(names))),
+            other => other.clone()
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        match self {
+            CCExpression::Def(_, args) => {
+                1 + args.iter().map(|x| x.size()).sum::<u32>()
+            },
+            CCExpression::Application(lhs, rhs) => 1 + lhs.size() + rhs.size(),
+            CCExpression::Abs(_, a_type, ret) => 1 + a_type.size() + ret.size(),
+            CCExpression::TypeAbs(_, a_type, ret) => 1 + a_type.size() + ret.size(),
+            _ => 1
+        }
+    }
 }
 
 impl Clone for CCExpression {
@@ -413,6 +572,7 @@ impl Clone for CCExpression {
             CCExpression::Var(x) => CCExpression::Var(x.clone()),
             CCExpression::Sq => CCExpression::Sq,
             CCExpression::Star => CCExpression::Star,
+            CCExpression::Universe(i) => CCExpression::Universe(*i),
             CCExpression::Prim => CCExpression::Prim,
             CCExpression::Def(name, args) => {
                 CCExpression::Def(name.clone(), args.clone())
@@ -656,4 +816,75 @@ mod tests {
         assert_eq!(expr6.beta_reduce().to_latex(), "\\lambda x : A . x");
 
     }
+
+    #[test]
+    fn alpha_equiv_capture() {
+        let var = |x: &str| Box::new(CCExpression::Var(x.to_string()));
+        let app = |f: &str, x: &str| Box::new(CCExpression::Application(var(f), var(x)));
+        let lhs = CCExpression::TypeAbs("a".to_string(), var("A"),
+            Box::new(CCExpression::TypeAbs("p".to_string(), app("a", "x"), app("a", "x"))));
+        let rhs = CCExpression::TypeAbs("B".to_string(), var("A"),
+            Box::new(CCExpression::TypeAbs("a".to_string(), app("B", "x"), app("B", "x"))));
+        let other = CCExpression::TypeAbs("B".to_string(), var("A"),
+            Box::new(CCExpression::TypeAbs("a".to_string(), app("B", "x"), app("a", "x"))));
+        assert!(lhs.alpha_equiv(&rhs));
+        assert!(!lhs.alpha_equiv(&other));
+    }
+
+    #[test]
+    fn instantiate_avoids_capture() {
+        let var = |x: &str| Box::new(CCExpression::Var(x.to_string()));
+        let ex = CCExpression::TypeAbs("a".to_string(), var("A"), var("A"));
+        assert_eq!(ex.substitute("A", &var("a")).to_latex(), "\\prod a : a . a");
+        assert_eq!(ex.instantiate("A", &var("a")).to_latex(), "a \\to a");
+        let redex = CCExpression::Application(
+            Box::new(CCExpression::Abs("x".to_string(), var("A"), Box::new(ex.clone()))),
+            var("A"));
+        assert!(redex.beta_equiv(&ex));
+    }
+
+    #[test]
+    fn beta_reduce_full() {
+        let var = |x: &str| Box::new(CCExpression::Var(x.to_string()));
+        let id = Box::new(CCExpression::Abs("x".to_string(), var("A"), var("x")));
+        let under = CCExpression::Abs("y".to_string(), var("A"),
+            Box::new(CCExpression::Application(id.clone(), var("y"))));
+        assert_eq!(under.beta_reduce().to_latex(), "\\lambda y : A . y");
+        let again = CCExpression::Application(
+            Box::new(CCExpression::Abs("f".to_string(), var("B"),
+                Box::new(CCExpression::Application(var("f"), var("b"))))),
+            id);
+        assert_eq!(again.beta_reduce().to_latex(), "b");
+    }
+
+    #[test]
+    fn beta_reduce_bounded() {
+        let var = |x: &str| Box::new(CCExpression::Var(x.to_string()));
+        let self_appl = CCExpression::Abs("y".to_string(), var("A"),
+            Box::new(CCExpression::Application(var("y"), var("y"))));
+        let omega = CCExpression::Application(Box::new(self_appl.clone()), Box::new(self_appl));
+        assert_eq!(omega.beta_reduce(), omega);
+        assert!(!omega.beta_equiv(&CCExpression::Var("A".to_string())));
+    }
+
+    #[test]
+    fn size_simple() {
+        let expr1 = CCExpression::Var(String::from("x"));
+        let expr2 = CCExpression::Var(String::from("A"));
+        let expr3 = CCExpression::Abs(
+            String::from("x"),
+            Box::new(expr2.clone()),
+            Box::new(expr1.clone())
+            );
+        let expr4 = CCExpression::Application(
+            Box::new(expr3.clone()),
+            Box::new(expr1.clone())
+            );
+        let expr5 = CCExpression::Def("id".to_string(), vec![expr2.clone()]);
+
+        assert_eq!(expr1.size(), 1);
+        assert_eq!(expr3.size(), 3);
+        assert_eq!(expr4.size(), 5);
+        assert_eq!(expr5.size(), 2);
+    }
 }
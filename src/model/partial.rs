@@ -29,6 +29,34 @@ impl GoalCount {
     }
 }
 
+fn placeholder_idx(ex: &CCExpression) -> Option<usize> {
+    let name = ex.var_str()?;
+    name.strip_prefix("sub_{")?.strip_suffix("}")?.parse::<usize>().ok()
+}
+
+fn inst_min_size(inst: &CCExpression, subs: &[Goal]) -> u32 {
+    if let Some(idx) = placeholder_idx(inst) {
+        if idx < subs.len() {
+            return subs[idx].min_size();
+        }
+    }
+    match inst {
+        CCExpression::Def(_, args) => {
+            1 + args.iter().map(|x| inst_min_size(x, subs)).sum::<u32>()
+        },
+        CCExpression::Application(lhs, rhs) => {
+            1 + inst_min_size(lhs, subs) + inst_min_size(rhs, subs)
+        },
+        CCExpression::Abs(_, a_type, ret) => {
+            1 + a_type.size() + inst_min_size(ret, subs)
+        },
+        CCExpression::TypeAbs(_, a_type, ret) => {
+            1 + a_type.size() + inst_min_size(ret, subs)
+        },
+        _ => 1
+    }
+}
+
 #[derive(Debug,PartialEq,Eq,Clone)]
 pub struct WithConc {
     pub conc: Vec<Judgement>,
@@ -87,6 +115,14 @@ impl Goal {
         }
     }
 
+    pub fn min_size(&self) -> u32 {
+        match self {
+            Goal::Initial(_, _) => 1,
+            Goal::Unpacked(inst, _, lst, _) => inst_min_size(inst, lst),
+            Goal::Final(lst) => lst.last().map(|x| x.statement.subject.size()).unwrap_or(1)
+        }
+    }
+
     pub fn replace(&self, old_g: &Goal, new_g: &Goal) -> Goal {
         match self {
             Goal::Unpacked(term, ex, lst, ctx) => {
@@ -125,10 +161,11 @@ impl Goal {
     }
 }
 
-#[derive(Clone,PartialEq,Eq)]
+#[derive(Clone)]
 pub struct PartialSol {
     pub context: Vec<Statement>,
-    pub goals: Vec<Goal>
+    pub goals: Vec<Goal>,
+    pub strat: Option<String>
 }
 
 impl PartialSol {
@@ -147,13 +184,18 @@ impl PartialSol {
                 );
     }
 
+    pub fn min_size(&self) -> u32 {
+        self.goals.iter().map(|x| x.min_size()).sum()
+    }
+
     pub fn replace(&self, old_g: &Goal, new_g: &Goal) -> PartialSol {
         PartialSol {
             context: self.context.clone(),
             goals: self.goals.iter().map(
                 |x| if x == old_g { new_g.clone() }
                 else { x.replace(old_g, new_g) }
-                ).collect()
+                ).collect(),
+            strat: self.strat.clone()
         }
     }
 
@@ -164,9 +206,17 @@ impl PartialSol {
     }
 }
 
+impl PartialEq for PartialSol {
+    fn eq(&self, rhs: &PartialSol) -> bool {
+        self.context == rhs.context && self.goals == rhs.goals
+    }
+}
+
+impl Eq for PartialSol {}
+
 impl Hash for PartialSol {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.goals.last().unwrap().to_latex().hash(state);
+        self.to_latex().hash(state);
     }
 }
 
@@ -192,12 +242,36 @@ mod tests {
         assert_eq!(g1.to_latex(), "?? : A \\to A (ctx: a : A)");
         let partial = PartialSol{
             context: vec![stmt1],
-            goals: vec![g1]
+            goals: vec![g1],
+            strat: None
         };
         assert_eq!(partial.to_latex(), "A : \\ast\n?? : A \\to A (ctx: a : A)");
         assert_eq!(partial.count(), GoalCount {i: 1, u: 0, f:0});
     }
 
+    #[test]
+    fn hash_whole_state() {
+        let key = |x: &PartialSol| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            x.hash(&mut hasher);
+            hasher.finish()
+        };
+        let t1 = CCExpression::Var("A".to_string());
+        let t2 = CCExpression::Var("B".to_string());
+        let lhs = PartialSol {
+            context: vec![],
+            goals: vec![Goal::Initial(t1.clone(), vec![]), Goal::Initial(t2.clone(), vec![])],
+            strat: None
+        };
+        let rhs = PartialSol {
+            context: vec![],
+            goals: vec![Goal::Initial(t2.clone(), vec![]), Goal::Initial(t2.clone(), vec![])],
+            strat: Some("var".to_string())
+        };
+        assert_ne!(key(&lhs), key(&rhs));
+        assert_eq!(key(&lhs), key(&PartialSol { strat: Some("var".to_string()), ..lhs.clone() }));
+    }
+
     #[test]
     fn unpacked_to_latex() {
         let t1 = CCExpression::Var("A".to_string());
@@ -221,13 +295,15 @@ mod tests {
         assert_eq!(g3.to_latex(), " ?? : A \\to A\n?? : A \\to A");
         let partial = PartialSol{
             context: vec![stmt1],
-            goals: vec![g2]
+            goals: vec![g2],
+            strat: None
         };
         let partial2 = partial.replace(&Goal::Initial(t1.clone(), vec![]), &Goal::Initial(t2.clone(), vec![]));
         assert_eq!(partial.to_latex(), "A : \\ast\n ?? : A\n?? : A \\to A");
         assert_eq!(partial2.to_latex(), "A : \\ast\n ?? : A \\to A\n?? : A \\to A");
         assert_eq!(partial.count(), GoalCount {i: 1, u: 1, f:0});
         assert_eq!(partial2.count(), GoalCount {i: 1, u: 1, f:0});
+        assert_eq!(partial.min_size(), 1);
         let act = partial.active();
         assert_eq!(act.len(), 1);
         assert_eq!(act.last().unwrap().goal.to_latex(), "?? : A");
@@ -261,10 +337,12 @@ mod tests {
         assert_eq!(g1.to_latex(), "A : \\ast \\vdash \\lambda x : A . x : A \\to A");
         let partial = PartialSol{
             context: vec![stmt1],
-            goals: vec![g1.clone()]
+            goals: vec![g1.clone()],
+            strat: None
         };
         assert_eq!(partial.to_latex(), "A : \\ast\nA : \\ast \\vdash \\lambda x : A . x : A \\to A");
         assert_eq!(partial.count(), GoalCount {i: 0, u: 0, f:1});
+        assert_eq!(partial.min_size(), 3);
 
         let g2 = Goal::Initial(CCExpression::Var("A".to_string()), vec![]);
         let g3 = Goal::Initial(CCExpression::Var("B".to_string()), vec![]);
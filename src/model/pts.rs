@@ -0,0 +1,183 @@
+use super::expression::{CCExpression};
+
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct PtsSpec {
+    pub name: String,
+    pub sorts: Vec<CCExpression>,
+    pub axioms: Vec<(CCExpression, CCExpression)>,
+    pub rules: Vec<(CCExpression, CCExpression, CCExpression)>,
+    pub cumulative: bool
+}
+
+pub const ECC_LEVELS: u32 = 4;
+
+fn cube(name: &str, pairs: &[(CCExpression, CCExpression)]) -> PtsSpec {
+    PtsSpec {
+        name: name.to_string(),
+        sorts: vec![CCExpression::Star, CCExpression::Sq],
+        axioms: vec![(CCExpression::Star, CCExpression::Sq)],
+        rules: pairs.iter().map(|(s1, s2)| (s1.clone(), s2.clone(), s2.clone())).collect(),
+        cumulative: false
+    }
+}
+
+impl PtsSpec {
+    pub fn stlc() -> PtsSpec {
+        cube("stlc", &[(CCExpression::Star, CCExpression::Star)])
+    }
+
+    pub fn lambda2() -> PtsSpec {
+        cube("l2", &[(CCExpression::Star, CCExpression::Star),
+                     (CCExpression::Sq, CCExpression::Star)])
+    }
+
+    pub fn lambda_p() -> PtsSpec {
+        cube("lp", &[(CCExpression::Star, CCExpression::Star),
+                     (CCExpression::Star, CCExpression::Sq)])
+    }
+
+    pub fn lambda_omega() -> PtsSpec {
+        cube("lw", &[(CCExpression::Star, CCExpression::Star),
+                     (CCExpression::Sq, CCExpression::Sq)])
+    }
+
+    pub fn coc() -> PtsSpec {
+        cube("coc", &[(CCExpression::Star, CCExpression::Star),
+                      (CCExpression::Sq, CCExpression::Star),
+                      (CCExpression::Star, CCExpression::Sq),
+                      (CCExpression::Sq, CCExpression::Sq)])
+    }
+
+    pub fn ecc() -> PtsSpec {
+        let universes: Vec<CCExpression> = [CCExpression::Sq].into_iter().chain(
+            (1..ECC_LEVELS).map(CCExpression::Universe)).collect();
+        let mut rules = vec![(CCExpression::Star, CCExpression::Star, CCExpression::Star)];
+        for u1 in &universes {
+            rules.push((u1.clone(), CCExpression::Star, CCExpression::Star));
+            rules.push((CCExpression::Star, u1.clone(), u1.clone()));
+            for u2 in &universes {
+                let top = if PtsSpec::level(u1) > PtsSpec::level(u2) { u1 } else { u2 };
+                rules.push((u1.clone(), u2.clone(), top.clone()));
+            }
+        }
+        PtsSpec {
+            name: "ecc".to_string(),
+            sorts: [vec![CCExpression::Star], universes.clone()].concat(),
+            axioms: [vec![(CCExpression::Star, CCExpression::Sq)],
+                     universes.windows(2).map(|w| (w[0].clone(), w[1].clone())).collect()].concat(),
+            rules,
+            cumulative: true
+        }
+    }
+
+    pub fn preset_names() -> Vec<String> {
+        ["stlc", "l2", "lp", "lw", "coc", "ecc"].iter().map(|x| x.to_string()).collect()
+    }
+
+    pub fn preset(name: &str) -> Result<PtsSpec, String> {
+        match name {
+            "stlc" => Ok(PtsSpec::stlc()),
+            "l2" => Ok(PtsSpec::lambda2()),
+            "lp" => Ok(PtsSpec::lambda_p()),
+            "lw" => Ok(PtsSpec::lambda_omega()),
+            "coc" => Ok(PtsSpec::coc()),
+            "ecc" => Ok(PtsSpec::ecc()),
+            _ => Err(format!("unknown system: {} (known: {})", name, PtsSpec::preset_names().join(", ")))
+        }
+    }
+
+    pub fn is_sort(&self, ex: &CCExpression) -> bool {
+        self.sorts.contains(ex)
+    }
+
+    pub fn axiom(&self, s1: &CCExpression, s2: &CCExpression) -> bool {
+        self.axioms.iter().any(|(a, b)| a == s1 && b == s2)
+    }
+
+    pub fn axiom_of(&self, s1: &CCExpression) -> Option<CCExpression> {
+        self.axioms.iter().find(|(a, _)| a == s1).map(|(_, b)| b.clone())
+    }
+
+    pub fn rule(&self, s1: &CCExpression, s2: &CCExpression) -> Option<CCExpression> {
+        self.rules.iter().find(|(a, b, _)| a == s1 && b == s2).map(|(_, _, c)| c.clone())
+    }
+
+    fn level(ex: &CCExpression) -> Option<u32> {
+        match ex {
+            CCExpression::Star => Some(0),
+            CCExpression::Sq => Some(1),
+            CCExpression::Universe(i) => Some(i + 1),
+            _ => None
+        }
+    }
+
+    pub fn sort_leq(&self, s1: &CCExpression, s2: &CCExpression) -> bool {
+        if s1 == s2 {
+            return true;
+        }
+        match (PtsSpec::level(s1), PtsSpec::level(s2)) {
+            (Some(l1), Some(l2)) => self.cumulative && l1 <= l2,
+            _ => false
+        }
+    }
+
+    pub fn subtype(&self, lhs: &CCExpression, rhs: &CCExpression) -> bool {
+        if lhs.beta_equiv(rhs) {
+            return true;
+        }
+        if !self.cumulative {
+            return false;
+        }
+        match (lhs.beta_reduce(), rhs.beta_reduce()) {
+            (CCExpression::TypeAbs(x, t1, r1), CCExpression::TypeAbs(y, t2, r2)) => {
+                t1.beta_equiv(&t2) && self.subtype(&r1, &r2.substitute(&y, &CCExpression::Var(x)))
+            },
+            (l, r) => l.is_sort() && r.is_sort() && self.sort_leq(&l, &r)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_rules() {
+        let star = CCExpression::Star;
+        let sq = CCExpression::Sq;
+        let all = [(&star, &star), (&sq, &star), (&star, &sq), (&sq, &sq)];
+        let allowed = |spec: &PtsSpec| all.iter().map(
+            |(a, b)| spec.rule(a, b).is_some()).collect::<Vec<bool>>();
+        assert_eq!(allowed(&PtsSpec::stlc()), [true, false, false, false]);
+        assert_eq!(allowed(&PtsSpec::lambda2()), [true, true, false, false]);
+        assert_eq!(allowed(&PtsSpec::lambda_p()), [true, false, true, false]);
+        assert_eq!(allowed(&PtsSpec::lambda_omega()), [true, false, false, true]);
+        assert_eq!(allowed(&PtsSpec::coc()), [true, true, true, true]);
+        assert_eq!(PtsSpec::coc().rule(&sq, &star), Some(star.clone()));
+        assert!(PtsSpec::coc().axiom(&star, &sq));
+        assert!(PtsSpec::preset("lambda").is_err());
+    }
+
+    #[test]
+    fn ecc_universes() {
+        let ecc = PtsSpec::ecc();
+        let t1 = CCExpression::Universe(1);
+        let t2 = CCExpression::Universe(2);
+        assert!(ecc.axiom(&CCExpression::Sq, &t1));
+        assert!(ecc.axiom(&t1, &t2));
+        assert_eq!(ecc.rule(&t2, &CCExpression::Sq), Some(t2.clone()));
+        assert_eq!(ecc.rule(&t1, &CCExpression::Star), Some(CCExpression::Star));
+        assert!(ecc.sort_leq(&CCExpression::Star, &t2));
+        assert!(!ecc.sort_leq(&t2, &t1));
+        assert!(!PtsSpec::coc().sort_leq(&CCExpression::Star, &CCExpression::Sq));
+
+        let a = CCExpression::Var("A".to_string());
+        let arrow = |x: &str, r: &CCExpression| CCExpression::TypeAbs(
+            x.to_string(), Box::new(a.clone()), Box::new(r.clone()));
+        assert!(ecc.subtype(&arrow("x", &CCExpression::Star), &arrow("y", &t1)));
+        assert!(!ecc.subtype(&arrow("x", &t1), &arrow("y", &CCExpression::Star)));
+        assert!(!PtsSpec::coc().subtype(&arrow("x", &CCExpression::Star), &arrow("y", &t1)));
+    }
+}
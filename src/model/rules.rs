@@ -9,6 +9,10 @@ mod abst;
 mod conv;
 mod inst;
 mod ruleset;
+mod index;
 
 pub use self::base::*;
-pub use self::ruleset::all_rules;
+pub use self::ruleset::{all_rules_in};
+#[cfg(test)]
+pub use self::ruleset::{all_rules};
+pub use self::index::PremiseIndex;
@@ -2,22 +2,11 @@
 use crate::model::expression::CCExpression;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
+use crate::model::rules::index::{PremiseIndex};
 use crate::model::rules::base::{DerRule, abst_alt_equiv};
 
-fn find_matching_stmt(context: &[Statement], stmt: &CCExpression) -> Option<Statement> {
-    for x in context {
-        if &x.s_type == stmt {
-            return Some(x.clone());
-        }
-    }
-    return None
-}
-
-fn make_new_ctx(context: &[Statement], stmt: &Statement) -> Vec<Statement> {
-    let ctx: Vec<Statement> = context.iter().filter_map(
-        |s| if s != stmt {Some(s.clone())} else {None}
-        ).collect();
-    return ctx
+fn last_matching_stmt(context: &[Statement], stmt: &CCExpression) -> Option<Statement> {
+    context.last().filter(|x| x.s_type.alpha_equiv(stmt)).cloned()
 }
 
 pub struct AbstRule {}
@@ -27,13 +16,14 @@ impl DerRule for AbstRule {
         if let Some(a_jdg) = lhs {
             if let Some(t_jdg) = rhs {
                 if let CCExpression::TypeAbs(_ph, a_type, r_type) = &t_jdg.statement.subject {
-                    if a_jdg.statement.s_type != **r_type {
-                        return None;
-                    }
-                    let o_m_stmt = find_matching_stmt(&a_jdg.context, &*a_type);
+                    let o_m_stmt = last_matching_stmt(&a_jdg.context, a_type);
                     if let Some(m_stmt) = o_m_stmt {
-                        let new_ctx = make_new_ctx(&a_jdg.context, &m_stmt);
+                        let new_ctx = a_jdg.context[..a_jdg.context.len() - 1].to_vec();
                         if let CCExpression::Var(ph2) = m_stmt.subject {
+                            let opened = r_type.substitute(_ph, &CCExpression::Var(ph2.clone()));
+                            if !a_jdg.statement.s_type.alpha_equiv(&opened) {
+                                return None;
+                            }
                             let stmt = Statement {
                                 subject: CCExpression::Abs(
                                              ph2,
@@ -63,6 +53,22 @@ impl DerRule for AbstRule {
 
     fn sig_size(&self) -> u32 { return 2; }
 
+    fn candidates(&self, index: &PremiseIndex, result: &Judgement) -> Option<Vec<Vec<usize>>> {
+        let size = result.statement.s_type.size();
+        let mut output: Vec<Vec<usize>> = vec![];
+        for t in index.with_subject_size(size) {
+            if let CCExpression::TypeAbs(x, _, r_type) = &index.lines[*t].statement.subject {
+                for a in index.with_open_type(r_type, x) {
+                    if index.lines[*a].context.len() > result.context.len() {
+                        output.push(vec![*a, *t]);
+                    }
+                }
+            }
+        }
+        output.sort();
+        Some(output)
+    }
+
     fn validate(&self, lhs: Option<&Judgement>, rhs: Option<&Judgement>,
                     result: &Judgement) -> bool {
         if let Some(j) = self.apply(lhs, rhs) {
@@ -77,6 +83,7 @@ impl DerRule for AbstRule {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::{parse_judgement};
 
     #[test]
     fn simple_abst() {
@@ -137,4 +144,22 @@ mod tests {
             panic!();
         }
     }
+
+    #[test]
+    fn abst_discharges_last() {
+        let rule = AbstRule {};
+        let body = parse_judgement("A : \\ast, x : A, y : A \\vdash x : A").unwrap();
+        let prod = parse_judgement("A : \\ast, x : A \\vdash A \\to A : \\ast").unwrap();
+        assert_eq!(rule.apply(Some(&body), Some(&prod)).unwrap().to_latex(),
+                   "A : \\ast, x : A \\vdash \\lambda y : A . x : A \\to A");
+
+        let body = parse_judgement("A : \\ast, P : A \\to \\ast, a : A \\vdash h : P a").unwrap();
+        let prod = parse_judgement("A : \\ast, P : A \\to \\ast \\vdash \\prod x : A . P x : \\ast").unwrap();
+        assert_eq!(rule.apply(Some(&body), Some(&prod)).unwrap().to_latex(),
+                   "A : \\ast, P : A \\to \\ast \\vdash \\lambda a : A . h : \\prod x : A . P x");
+
+        let other = parse_judgement("A : \\ast, B : \\ast \\vdash A \\to A : \\ast").unwrap();
+        let body = parse_judgement("A : \\ast, x : A, B : \\ast \\vdash x : A").unwrap();
+        assert!(rule.apply(Some(&body), Some(&other)).is_none());
+    }
 }
@@ -2,6 +2,7 @@
 use crate::model::expression::CCExpression;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
+use crate::model::rules::index::{PremiseIndex};
 use crate::model::rules::base::{DerRule};
 
 pub struct ApplRule {}
@@ -20,7 +21,7 @@ impl DerRule for ApplRule {
                                      Box::new(f_jdg.statement.subject.clone()),
                                      Box::new(a_jdg.statement.subject.clone())
                                      ),
-                        s_type: r_type.substitute(ph, &a_jdg.statement.subject)
+                        s_type: r_type.instantiate(ph, &a_jdg.statement.subject)
                     };
                     return Some(Judgement {
                         defs: f_jdg.defs.clone(),
@@ -38,6 +39,27 @@ impl DerRule for ApplRule {
     }
 
     fn sig_size(&self) -> u32 { return 2; }
+
+    fn candidates(&self, index: &PremiseIndex, result: &Judgement) -> Option<Vec<Vec<usize>>> {
+        let (f_size, a_size) = match &result.statement.subject {
+            CCExpression::Application(lhs, rhs) => (lhs.size(), rhs.size()),
+            _ => { return Some(vec![]); }
+        };
+        let mut output: Vec<Vec<usize>> = vec![];
+        for f in index.with_subject_size(f_size) {
+            if index.lines[*f].context.len() != result.context.len() {
+                continue;
+            }
+            if let CCExpression::TypeAbs(_, a_type, _) = &index.lines[*f].statement.s_type {
+                for a in index.with_type(a_type) {
+                    if index.sizes[*a] == a_size {
+                        output.push(vec![*f, *a]);
+                    }
+                }
+            }
+        }
+        Some(output)
+    }
 }
 
 #[cfg(test)]
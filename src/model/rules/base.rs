@@ -3,6 +3,7 @@ use crate::model::expression::CCExpression;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
 use crate::model::def::{Definition};
+use super::index::{PremiseIndex};
 
 fn alt_context(old_var: &String, new_var: &String, v_type: &CCExpression,
                context: &[Statement]) -> Vec<Statement> {
@@ -66,15 +67,9 @@ pub fn abst_alternatives(jdg: &Judgement) -> Vec<Judgement> {
 
 pub fn do_type_sub(s_type: &CCExpression, def: &Definition,
                arg_map: &Vec<Statement>) -> CCExpression {
-    let replacements: Vec<(&String, &CCExpression)> = def.args.iter()
-        .zip(arg_map.iter().map(|x| &x.subject)).collect();
-    let mut output: CCExpression = s_type.clone();
-
-    for (tok, rep) in replacements {
-        output = output.substitute(tok, rep);
-    }
-
-    return output;
+    let replacements: Vec<(String, CCExpression)> = def.args.iter().cloned()
+        .zip(arg_map.iter().map(|x| x.subject.clone())).collect();
+    s_type.instantiate_all(&replacements)
 }
 
 pub trait DerRule {
@@ -95,5 +90,15 @@ pub trait DerRule {
     fn validate_many(&self, _judges: &[Judgement], _result: &Judgement) -> Option<Vec<u32>> {
         return None;
     }
+
+    // checks result against the claimed premises of a rule with more than two
+    fn validate_premises(&self, _judges: &[Judgement], _premises: &[u32], _result: &Judgement) -> bool {
+        false
+    }
+
+    // premises (in line order) that could justify result, None to try every line
+    fn candidates(&self, _index: &PremiseIndex, _result: &Judgement) -> Option<Vec<Vec<usize>>> {
+        None
+    }
 }
 
@@ -1,9 +1,13 @@
 
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
+use crate::model::rules::index::{PremiseIndex, pairs};
 use crate::model::rules::base::{DerRule};
+use crate::model::pts::{PtsSpec};
 
-pub struct ConvRule {}
+pub struct ConvRule {
+    pub spec: PtsSpec
+}
 
 impl DerRule for ConvRule {
     fn apply(&self, lhs: Option<&Judgement>, rhs: Option<&Judgement>) -> Option<Judgement> {
@@ -11,7 +15,7 @@ impl DerRule for ConvRule {
             if let Some(other_judge) = rhs {
                 if !other_judge.statement.s_type.is_sort() { return None; }
                 if &orig_judge.statement.s_type == &other_judge.statement.subject { return None; }
-                if !&orig_judge.statement.s_type.beta_equiv(&other_judge.statement.subject) { return None; }
+                if !self.spec.subtype(&orig_judge.statement.s_type, &other_judge.statement.subject) { return None; }
 
                 let stmt = Statement {
                     subject: orig_judge.statement.subject.clone(),
@@ -32,6 +36,16 @@ impl DerRule for ConvRule {
     }
     
     fn sig_size(&self) -> u32 { return 2; }
+
+    fn candidates(&self, index: &PremiseIndex, result: &Judgement) -> Option<Vec<Vec<usize>>> {
+        let s_size = result.statement.subject.size();
+        let t_size = result.statement.s_type.size();
+        let orig: Vec<usize> = index.with_ctx_len(result.context.len()).iter().filter(
+            |x| index.sizes[**x] == s_size).cloned().collect();
+        let other: Vec<usize> = index.with_sort_type().iter().filter(
+            |x| index.sizes[**x] == t_size).cloned().collect();
+        Some(pairs(&orig, &other))
+    }
 }
 
 #[cfg(test)]
@@ -51,7 +65,7 @@ mod tests {
             Box::new(CCExpression::Var(String::from("A"))),
             Box::new(CCExpression::Var(String::from("y")))
             );
-        let rule = ConvRule {};
+        let rule = ConvRule { spec: PtsSpec::coc() };
         let stmt1 = Statement {
             subject: CCExpression::Var(String::from("x")),
             s_type: t1.clone()
@@ -2,9 +2,13 @@
 use crate::model::expression::CCExpression;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
+use crate::model::rules::index::{PremiseIndex, pairs};
 use crate::model::rules::base::{DerRule};
+use crate::model::pts::{PtsSpec};
 
-pub struct FormRule {}
+pub struct FormRule {
+    pub spec: PtsSpec
+}
 
 fn find_matching_stmt(context: &[Statement], stmt: &Statement) -> Option<Statement> {
     for x in context {
@@ -37,7 +41,7 @@ impl DerRule for FormRule {
                             );
                         let new_stmt = Statement {
                             subject: new_type,
-                            s_type: jdg2.statement.s_type.clone()
+                            s_type: self.spec.rule(&jdg1.statement.s_type, &jdg2.statement.s_type)?
                         };
                         return Some(Judgement {
                             defs: jdg1.defs.clone(),
@@ -57,14 +61,26 @@ impl DerRule for FormRule {
     
     fn sig_size(&self) -> u32 { return 2; }
 
+    fn candidates(&self, index: &PremiseIndex, result: &Judgement) -> Option<Vec<Vec<usize>>> {
+        match (&result.statement.subject, &result.statement.s_type) {
+            (CCExpression::TypeAbs(_, a_type, _), s) if self.spec.is_sort(s) => {
+                Some(pairs(index.with_subject(a_type), index.with_ctx_len(result.context.len() + 1)))
+            },
+            _ => Some(vec![])
+        }
+    }
+
     fn validate(&self, lhs: Option<&Judgement>, rhs: Option<&Judgement>,
                     result: &Judgement) -> bool {
         if let Some(lex) = lhs {
-            if !lex.statement.s_type.is_sort() { return false; }
+            if !self.spec.is_sort(&lex.statement.s_type) { return false; }
             if let Some(rex) = rhs {
-                if !rex.statement.s_type.is_sort() { return false; }
+                if !self.spec.is_sort(&rex.statement.s_type) { return false; }
                 match (&result.statement.subject, &result.statement.s_type) {
-                    (CCExpression::TypeAbs(arg, a_type, ret), CCExpression::Star) => {
+                    (CCExpression::TypeAbs(arg, a_type, ret), s) => {
+                        if self.spec.rule(&lex.statement.s_type, &rex.statement.s_type).as_ref() != Some(s) {
+                            return false;
+                        }
                         if !a_type.alpha_equiv(&lex.statement.subject) { return false; }
                         if rex.context.len() != result.context.len() + 1 { return false; }
                         let last_stmt = rex.context.last().unwrap();
@@ -87,7 +103,7 @@ mod tests {
 
     #[test]
     fn simple_form_apply() {
-        let rule = FormRule {};
+        let rule = FormRule { spec: PtsSpec::coc() };
         let stmt1 = Statement {
             subject: CCExpression::Var(String::from("A")),
             s_type: CCExpression::Star
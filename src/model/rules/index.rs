@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use crate::model::judgement::{Judgement};
+use crate::model::expression::{CCExpression};
+
+
+pub struct PremiseIndex<'a> {
+    pub lines: Vec<&'a Judgement>,
+    pub sizes: Vec<u32>,
+    by_subject: HashMap<String, Vec<usize>>,
+    by_type: HashMap<String, Vec<usize>>,
+    by_open_type: HashMap<String, Vec<usize>>,
+    by_ctx_len: HashMap<usize, Vec<usize>>,
+    by_size: HashMap<u32, Vec<usize>>,
+    sort_typed: Vec<usize>
+}
+
+impl<'a> PremiseIndex<'a> {
+    pub fn blank() -> PremiseIndex<'a> {
+        PremiseIndex {
+            lines: vec![],
+            sizes: vec![],
+            by_subject: HashMap::new(),
+            by_type: HashMap::new(),
+            by_open_type: HashMap::new(),
+            by_ctx_len: HashMap::new(),
+            by_size: HashMap::new(),
+            sort_typed: vec![]
+        }
+    }
+
+    pub fn add(&mut self, jdg: &'a Judgement) {
+        let idx = self.lines.len();
+        self.lines.push(jdg);
+        self.by_subject.entry(jdg.statement.subject.canonical_key()).or_default().push(idx);
+        self.by_type.entry(jdg.statement.s_type.canonical_key()).or_default().push(idx);
+        self.by_ctx_len.entry(jdg.context.len()).or_default().push(idx);
+        if let Some(var) = jdg.context.last().and_then(|x| x.subject.var_str()) {
+            self.by_open_type.entry(open_key(&jdg.statement.s_type, &var)).or_default().push(idx);
+        }
+        let size = jdg.statement.subject.size();
+        self.sizes.push(size);
+        self.by_size.entry(size).or_default().push(idx);
+        if jdg.statement.s_type.is_sort() {
+            self.sort_typed.push(idx);
+        }
+    }
+
+    pub fn with_subject(&self, ex: &CCExpression) -> &[usize] {
+        self.by_subject.get(&ex.canonical_key()).map(|x| &x[..]).unwrap_or(&[])
+    }
+
+    pub fn with_type(&self, ex: &CCExpression) -> &[usize] {
+        self.by_type.get(&ex.canonical_key()).map(|x| &x[..]).unwrap_or(&[])
+    }
+
+    pub fn with_open_type(&self, ex: &CCExpression, var: &str) -> &[usize] {
+        self.by_open_type.get(&open_key(ex, var)).map(|x| &x[..]).unwrap_or(&[])
+    }
+
+    pub fn with_ctx_len(&self, len: usize) -> &[usize] {
+        self.by_ctx_len.get(&len).map(|x| &x[..]).unwrap_or(&[])
+    }
+
+    pub fn with_subject_size(&self, size: u32) -> &[usize] {
+        self.by_size.get(&size).map(|x| &x[..]).unwrap_or(&[])
+    }
+
+    pub fn with_sort_type(&self) -> &[usize] {
+        &self.sort_typed
+    }
+}
+
+fn open_key(ex: &CCExpression, var: &str) -> String {
+    ex.substitute(var, &CCExpression::Var("open_{}".to_string())).canonical_key()
+}
+
+pub fn pairs(lhs: &[usize], rhs: &[usize]) -> Vec<Vec<usize>> {
+    lhs.iter().flat_map(|l| rhs.iter().map(move |r| vec![*l, *r])).collect()
+}
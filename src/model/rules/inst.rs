@@ -27,32 +27,37 @@ fn build_arg_map(def: &Definition, known_args: &[(usize, Statement)],
             ).map(|x| x.clone()).collect());
 }
 
-fn find_jdg_for_def(def: &Definition, args: &[CCExpression],
+// the first arguments are taken from the claimed lines, the others are searched for
+fn find_jdg_for_def(def: &Definition, args: &[CCExpression], claimed: &[u32],
                     judges: &[Judgement], result: &Judgement) -> Option<Vec<u32>> {
-    let usable_stmts: Vec<(usize, Statement)> = judges.iter().enumerate().
-        filter_map(|(idx, jdg)| if result.weaker_eq(jdg) {
-            Some((idx, jdg.statement.clone()))
-        } else {
-            None
-        }).collect();
-    if let Some(known) = build_arg_map(def, &usable_stmts, args) {
-        let arg_names: Vec<CCExpression> = known.iter().map(
-            |arg| arg.1.subject.clone()).collect();
-        let new_def = CCExpression::Def(def.name.clone(), arg_names);
-        let new_stmt = Statement {
-            subject: new_def,
-            s_type: do_type_sub(&def.body.s_type, &def,
-                                &known.iter().map(
-                                    |x| x.1.clone()).collect())
+    let arg_types: Vec<CCExpression> = def.type_list()?;
+    let mut known: Vec<(usize, Statement)> = vec![];
+    for (pos, (argt, uarg)) in arg_types.iter().zip(args).enumerate() {
+        let done: Vec<Statement> = known.iter().map(|x| x.1.clone()).collect();
+        let expected = do_type_sub(argt, def, &done);
+        let fits = |jdg: &Judgement| result.weaker_eq(jdg) && jdg.statement.subject == *uarg
+                && jdg.statement.s_type.alpha_equiv(&expected);
+        let found = match claimed.get(pos) {
+            Some(line) => {
+                let jdg = judges.get(*line as usize).filter(|jdg| fits(jdg))?;
+                (*line as usize, jdg)
+            },
+            None => judges.iter().enumerate().find(|(_, jdg)| fits(jdg))?
         };
-        let new_jdg = Judgement {
-            defs: result.defs.clone(),
-            context: result.context.clone(),
-            statement: new_stmt
-        };
-        if new_jdg.alpha_equiv(result) {
-            return Some(known.iter().map(|(idx, _)| *idx as u32).collect());
-        }
+        known.push((found.0, found.1.statement.clone()));
+    }
+    let new_stmt = Statement {
+        subject: CCExpression::Def(def.name.clone(), args.to_vec()),
+        s_type: do_type_sub(&def.body.s_type, def,
+                            &known.iter().map(|x| x.1.clone()).collect())
+    };
+    let new_jdg = Judgement {
+        defs: result.defs.clone(),
+        context: result.context.clone(),
+        statement: new_stmt
+    };
+    if new_jdg.alpha_equiv(result) {
+        return Some(known.iter().map(|(idx, _)| *idx as u32).collect());
     }
     return None;
 }
@@ -87,7 +92,7 @@ impl DerRule for InstRule {
         if let CCExpression::Def(name, args) = &result.statement.subject {
             for def in &self.defs {
                 if def.name == *name && def.args.len() == args.len() {
-                    let lines = find_jdg_for_def(def, args, judges, result);
+                    let lines = find_jdg_for_def(def, args, &[], judges, result);
                     if let Some(_) = lines {
                         return lines;
                     }
@@ -97,6 +102,18 @@ impl DerRule for InstRule {
         return None
     }
 
+    fn validate_premises(&self, judges: &[Judgement], premises: &[u32], result: &Judgement) -> bool {
+        if let CCExpression::Def(name, args) = &result.statement.subject {
+            if premises.len() != args.len().min(2) {
+                return false;
+            }
+            return self.defs.iter().any(
+                |def| def.name == *name && def.args.len() == args.len()
+                    && find_jdg_for_def(def, args, premises, judges, result).is_some());
+        }
+        false
+    }
+
     fn name(&self) -> String {
         return String::from("inst");
     }
@@ -9,18 +9,24 @@ use super::conv::ConvRule;
 use super::inst::InstRule;
 
 use crate::model::def::Definition;
+use crate::model::pts::PtsSpec;
 
 
+#[cfg(test)]
 pub fn all_rules(defs: &[Definition]) -> Vec<Box<dyn DerRule>> {
+    all_rules_in(&PtsSpec::coc(), defs)
+}
+
+pub fn all_rules_in(spec: &PtsSpec, defs: &[Definition]) -> Vec<Box<dyn DerRule>> {
     return vec![
-        Box::new(SortRule {}),
+        Box::new(SortRule { spec: spec.clone() }),
         Box::new(VarRule {}),
         Box::new(WeakRule {}),
-        Box::new(FormRule {}),
+        Box::new(FormRule { spec: spec.clone() }),
         Box::new(ApplRule {}),
         Box::new(AbstRule {}),
         Box::new(InstRule { defs: defs.to_vec() }),
-        Box::new(ConvRule {})
+        Box::new(ConvRule { spec: spec.clone() })
     ];
 }
 
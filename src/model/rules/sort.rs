@@ -1,18 +1,22 @@
 
-use crate::model::expression::CCExpression;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
 use crate::model::rules::base::{DerRule};
+use crate::model::pts::{PtsSpec};
 
-pub struct SortRule {}
+pub struct SortRule {
+    pub spec: PtsSpec
+}
 
 impl DerRule for SortRule {
     fn apply(&self, lhs: Option<&Judgement>, rhs: Option<&Judgement>) -> Option<Judgement> {
         if let Some(_) = lhs { return None; }
         if let Some(_) = rhs { return None; }
+        // a sort line has no premises to pick an axiom by, validate accepts all of them
+        let s1 = self.spec.sorts.iter().find(|s| self.spec.axiom_of(s).is_some())?;
         let stmt = Statement {
-            subject: CCExpression::Star,
-            s_type: CCExpression::Sq
+            subject: s1.clone(),
+            s_type: self.spec.axiom_of(s1)?
         };
         return Some(Judgement {
             defs: vec![],
@@ -26,15 +30,23 @@ impl DerRule for SortRule {
     }
     
     fn sig_size(&self) -> u32 { return 0; }
+
+    fn validate(&self, lhs: Option<&Judgement>, rhs: Option<&Judgement>,
+                    result: &Judgement) -> bool {
+        if lhs.is_some() || rhs.is_some() { return false; }
+        result.context.is_empty() &&
+            self.spec.axiom(&result.statement.subject, &result.statement.s_type)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::expression::CCExpression;
 
     #[test]
     fn tokenize_sort() {
-        let rule = SortRule {};
+        let rule = SortRule { spec: PtsSpec::coc() };
         let stmt = Statement {
             subject: CCExpression::Star,
             s_type: CCExpression::Sq
@@ -48,4 +60,18 @@ mod tests {
             panic!();
         }
     }
+
+    #[test]
+    fn every_axiom_validates() {
+        let rule = SortRule { spec: PtsSpec::ecc() };
+        for (s1, s2) in &rule.spec.axioms {
+            let jdg = Judgement {
+                defs: vec![],
+                context: vec![],
+                statement: Statement { subject: s1.clone(), s_type: s2.clone() }
+            };
+            assert!(rule.validate(None, None, &jdg));
+        }
+        assert_eq!(rule.apply(None, None).unwrap().statement.subject, CCExpression::Star);
+    }
 }
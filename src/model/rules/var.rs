@@ -2,6 +2,7 @@
 use crate::model::expression::CCExpression;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
+use crate::model::rules::index::{PremiseIndex};
 use crate::model::rules::base::{DerRule};
 
 
@@ -52,6 +53,13 @@ impl DerRule for VarRule {
 
     fn sig_size(&self) -> u32 { return 1; }
 
+    fn candidates(&self, index: &PremiseIndex, result: &Judgement) -> Option<Vec<Vec<usize>>> {
+        if result.context.is_empty() {
+            return Some(vec![]);
+        }
+        Some(index.with_ctx_len(result.context.len() - 1).iter().map(|x| vec![*x]).collect())
+    }
+
     fn validate(&self, lhs: Option<&Judgement>, rhs: Option<&Judgement>,
                     result: &Judgement) -> bool {
         if let Some(_) = rhs { return false; }
@@ -2,6 +2,7 @@
 use crate::model::expression::CCExpression;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
+use crate::model::rules::index::{PremiseIndex, pairs};
 use crate::model::rules::base::{DerRule};
 
 pub struct WeakRule {}
@@ -33,6 +34,14 @@ impl DerRule for WeakRule {
     
     fn sig_size(&self) -> u32 { return 2; }
 
+    fn candidates(&self, index: &PremiseIndex, result: &Judgement) -> Option<Vec<Vec<usize>>> {
+        match result.context.last() {
+            Some(last) => Some(pairs(index.with_subject(&result.statement.subject),
+                                     index.with_subject(&last.s_type))),
+            None => Some(vec![])
+        }
+    }
+
     fn validate(&self, lhs: Option<&Judgement>, rhs: Option<&Judgement>,
                     result: &Judgement) -> bool {
         if let Some(lex) = lhs {
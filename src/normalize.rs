@@ -0,0 +1,180 @@
+use crate::model::expression::{CCExpression};
+use crate::model::def::{Definition};
+use crate::model::judgement::{Judgement};
+use crate::model::statement::{Statement};
+use crate::environment::{Environment};
+use crate::parser::{parse, parse_judgement};
+
+
+const STEP_LIMIT: usize = 1000;
+
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Reduction {
+    Beta,
+    Delta
+}
+
+impl Reduction {
+    pub fn to_latex(self) -> &'static str {
+        match self {
+            Reduction::Beta => "\\to_{\\beta}",
+            Reduction::Delta => "\\to_{\\delta}"
+        }
+    }
+}
+
+fn unfold(name: &str, args: &[CCExpression], defs: &[Definition]) -> Option<CCExpression> {
+    defs.iter().find(|d| d.name == name)?.unfold(args)
+}
+
+pub fn reduce_step(ex: &CCExpression, defs: &[Definition]) -> Option<(Reduction, CCExpression)> {
+    match ex {
+        CCExpression::Application(lhs, rhs) => {
+            if let CCExpression::Abs(x, _, ret) = &**lhs {
+                return Some((Reduction::Beta, ret.instantiate(x, rhs)));
+            }
+            if let Some((r, l)) = reduce_step(lhs, defs) {
+                return Some((r, CCExpression::Application(Box::new(l), rhs.clone())));
+            }
+            reduce_step(rhs, defs).map(
+                |(r, new_rhs)| (r, CCExpression::Application(lhs.clone(), Box::new(new_rhs))))
+        },
+        CCExpression::Def(name, args) => {
+            if let Some(body) = unfold(name, args, defs) {
+                return Some((Reduction::Delta, body));
+            }
+            for (idx, arg) in args.iter().enumerate() {
+                if let Some((r, new_arg)) = reduce_step(arg, defs) {
+                    let mut new_args = args.to_vec();
+                    new_args[idx] = new_arg;
+                    return Some((r, CCExpression::Def(name.clone(), new_args)));
+                }
+            }
+            None
+        },
+        CCExpression::Abs(x, a_type, ret) | CCExpression::TypeAbs(x, a_type, ret) => {
+            let rebuild = |a: CCExpression, b: CCExpression| match ex {
+                CCExpression::Abs(..) => CCExpression::Abs(x.clone(), Box::new(a), Box::new(b)),
+                _ => CCExpression::TypeAbs(x.clone(), Box::new(a), Box::new(b))
+            };
+            if let Some((r, a)) = reduce_step(a_type, defs) {
+                return Some((r, rebuild(a, *ret.clone())));
+            }
+            reduce_step(ret, defs).map(|(r, b)| (r, rebuild(*a_type.clone(), b)))
+        },
+        _ => None
+    }
+}
+
+pub fn reduction_steps(ex: &CCExpression, defs: &[Definition],
+                       limit: usize) -> Result<Vec<(Reduction, CCExpression)>, String> {
+    let mut steps: Vec<(Reduction, CCExpression)> = vec![];
+    let mut current = ex.clone();
+    while let Some((r, next)) = reduce_step(&current, defs) {
+        if steps.len() >= limit {
+            return Err(format!("no normal form for {} after {} steps", ex.to_latex(), limit));
+        }
+        steps.push((r, next.clone()));
+        current = next;
+    }
+    Ok(steps)
+}
+
+pub fn normal_form(ex: &CCExpression, defs: &[Definition]) -> Result<CCExpression, String> {
+    let steps = reduction_steps(ex, defs, STEP_LIMIT)?;
+    Ok(steps.last().map_or(ex.clone(), |(_, term)| term.clone()))
+}
+
+pub fn align_chain(ex: &CCExpression, steps: &[(Reduction, CCExpression)]) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut lhs = ex.to_latex();
+    for (r, term) in steps {
+        lines.push(format!("{} &{} {}", lhs, r.to_latex(), term.to_latex()));
+        lhs = "".to_string();
+    }
+    if lines.is_empty() {
+        lines.push(lhs);
+    }
+    format!("\\begin{{align*}}\n{}\n\\end{{align*}}", lines.join(" \\\\\n"))
+}
+
+fn normalize_term(ex: &CCExpression, env: &Environment, steps: bool) -> Result<String, String> {
+    if steps {
+        Ok(align_chain(ex, &reduction_steps(ex, &env.defs, STEP_LIMIT)?))
+    } else {
+        Ok(normal_form(ex, &env.defs)?.to_latex())
+    }
+}
+
+pub fn normalize_input(expr: &str, env: &Environment, steps: bool) -> Result<String, String> {
+    if !expr.contains("\\vdash") {
+        let ex = env.resolve(&parse(expr).ok_or(format!("failed to parse: ({})", expr))?)?;
+        return normalize_term(&ex, env, steps);
+    }
+    let jdg = parse_judgement(expr).ok_or(format!("failed to parse: ({})", expr))?;
+    let subject = env.resolve(&jdg.statement.subject)?;
+    let s_type = env.resolve(&jdg.statement.s_type)?;
+    if steps {
+        return Ok(normalize_term(&subject, env, true)? + "\n" + &normalize_term(&s_type, env, true)?);
+    }
+    let out = Judgement {
+        statement: Statement {
+            subject: normal_form(&subject, &env.defs)?,
+            s_type: normal_form(&s_type, &env.defs)?
+        },
+        ..jdg
+    };
+    Ok(out.to_latex())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_definition};
+
+    #[test]
+    fn reduce_with_defs() {
+        let defs = vec![
+            parse_definition("A : \\ast \\vartriangleright id \\langle A \\rangle := \\lambda x : A . x : A \\to A").unwrap(),
+            parse_definition("A : \\ast \\vartriangleright lem \\langle A \\rangle := \\independent : \\neg A \\vee A").unwrap()
+        ];
+        let ex = parse("id \\langle B \\rangle ((\\lambda y : B . y) b)").unwrap();
+        assert_eq!(normal_form(&ex, &[]).unwrap().to_latex(), "id \\langle B \\rangle b");
+        assert_eq!(normal_form(&ex, &defs).unwrap().to_latex(), "b");
+
+        let steps = reduction_steps(&ex, &defs, 10).unwrap();
+        assert_eq!(steps.iter().map(|x| x.0).collect::<Vec<Reduction>>(),
+                   [Reduction::Delta, Reduction::Beta, Reduction::Beta]);
+        assert_eq!(align_chain(&ex, &steps),
+                   "\\begin{align*}\n\
+                    id \\langle B \\rangle ((\\lambda y : B . y) b) &\\to_{\\delta} (\\lambda x : B . x) ((\\lambda y : B . y) b) \\\\\n \
+                    &\\to_{\\beta} (\\lambda y : B . y) b \\\\\n \
+                    &\\to_{\\beta} b\n\
+                    \\end{align*}");
+
+        let prim = parse("lem \\langle B \\rangle").unwrap();
+        assert_eq!(normal_form(&prim, &defs).unwrap(), prim);
+        assert_eq!(align_chain(&prim, &[]), "\\begin{align*}\nlem \\langle B \\rangle\n\\end{align*}");
+
+        let omega = parse("(\\lambda x : A . x x) (\\lambda x : A . x x)").unwrap();
+        assert!(reduction_steps(&omega, &[], 5).is_err());
+        assert_eq!(normal_form(&omega, &[]).unwrap_err(),
+                   format!("no normal form for {} after {} steps", omega.to_latex(), STEP_LIMIT));
+    }
+
+    #[test]
+    fn normalize_inputs() {
+        let mut env = Environment::blank();
+        env.add_text("A : \\ast \\vartriangleright id \\langle A \\rangle := \\lambda x : A . x : A \\to A").unwrap();
+        assert_eq!(normalize_input("id \\langle B \\rangle b", &env, false).unwrap(), "b");
+        assert_eq!(normalize_input("B : \\ast, b : B \\vdash (\\lambda y : B . y) b : (\\lambda T : \\ast . T) B", &env, false).unwrap(),
+                   "B : \\ast, b : B \\vdash b : B");
+        assert_eq!(normalize_input("B : \\ast, b : B \\vdash b : B", &env, true).unwrap(),
+                   "\\begin{align*}\nb\n\\end{align*}\n\\begin{align*}\nB\n\\end{align*}");
+        assert!(normalize_input("\\lambda", &env, false).is_err());
+        assert!(normalize_input("(\\lambda x : A . x x) (\\lambda x : A . x x)", &env, false).unwrap_err()
+                .starts_with("no normal form for "));
+    }
+}
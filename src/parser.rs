@@ -5,6 +5,10 @@ use crate::model::judgement::Judgement;
 use crate::model::def::Definition;
 use crate::util::{*};
 
+fn universe_level(token: &str) -> Option<u32> {
+    token.strip_prefix("\\mathrm{Type}_{")?.strip_suffix("}")?.parse::<u32>().ok()
+}
+
 fn all_alpha_num(tokens: &[String]) -> bool {
     let meta_token: Vec<String> = vec![
         String::from(","),
@@ -28,7 +32,7 @@ fn all_alpha_num(tokens: &[String]) -> bool {
         String::from("\\wedge"),
         String::from("\\prod")];
     let assessment: Option<bool> = tokens.into_iter().map(
-        |t| !meta_token.contains(t)
+        |t| !meta_token.contains(t) && universe_level(t).is_none()
         ).reduce(|a, b| a && b);
     if let Some(x) = assessment {
         return x;
@@ -140,6 +144,9 @@ fn section_multi_delim<'a>(tokens: &'a[String], delims: &'a[String]) -> Vec<Vec<
 fn comma_delim_expressions(tokens: &[String]) -> Option<Vec<CCExpression>> {
     let mut output = vec![];
     let mut last: usize = 0;
+    if tokens.is_empty() {
+        return Some(output);
+    }
     for (idx, token) in tokens.iter().enumerate() {
         if idx >= last && token == "," {
             if let Some(expr) = find_expression(&tokens[last..idx]) {
@@ -231,6 +238,17 @@ impl TokenConsumer for SqConsumer {
     }
 }
 
+struct UniverseConsumer {}
+
+impl TokenConsumer for UniverseConsumer {
+    fn consume(&self, tokens: &[String]) -> Option<Consumed> {
+        let level = universe_level(tokens.first()?)?;
+        Some(Consumed {
+            expr: if level == 0 { CCExpression::Sq } else { CCExpression::Universe(level) },
+            remain: tokens[1..].to_vec()})
+    }
+}
+
 struct PrimConsumer {}
 
 impl TokenConsumer for PrimConsumer {
@@ -255,12 +273,11 @@ impl TokenConsumer for AbsConsumer {
         for (idx1, token1) in tokens.iter().enumerate() {
             if token1 == ":" && all_alpha_num(&tokens[1..idx1]) {
                 for (idx2, token2) in tokens.iter().enumerate() {
-                    if idx2 > idx1 + 1 && token2 == "." {
+                    if idx2 > idx1 + 1 && token2 == "." && is_balanced(&tokens[idx1+1..idx2]) {
                         let type_expr = find_expression(
                             &tokens[idx1+1..idx2]);
-                        let ret_expr = find_expression(&tokens[idx2+1..]);
                         if let Some(t) = type_expr {
-                            if let Some(ret) = ret_expr {
+                            if let Some(ret) = find_expression(&tokens[idx2+1..]) {
                                 if tokens[0] == "\\lambda" {
                                     return Some(Consumed {
                                         expr: CCExpression::Abs(
@@ -298,8 +315,9 @@ impl TokenConsumer for ToConsumer {
     fn consume(&self, tokens: &[String]) -> Option<Consumed> {
         if tokens.len() > 2 {
             for (idx1, token1) in tokens.iter().enumerate() {
-                if token1 == "\\to" {
+                if token1 == "\\to" && is_balanced(&tokens[0..idx1]) {
                     let ante = find_expression(&tokens[0..idx1]);
+                    if ante.is_none() { continue; }
                     let cnsq = find_expression(&tokens[idx1+1..]);
                     match (ante, cnsq) {
                         (Some(a), Some(c)) => {
@@ -560,6 +578,7 @@ fn consume_expressions(tokens: &[String]) -> Vec<CCExpression> {
         &ParenConsumer{},
         &StarConsumer{},
         &SqConsumer{},
+        &UniverseConsumer{},
         &PrimConsumer{},
         &DefConsumer{},
         &PerpConsumer{},
@@ -634,7 +653,9 @@ fn find_def_name(tokens: &[String]) -> Option<(String, Vec<String>)> {
             args.push(tokens[last..idx].join(" "));
             last = idx + 1;
         } else if idx >= last && [")".to_string(), "\\rangle".to_string()].contains(token) {
-            args.push(tokens[last..idx].join(" "));
+            if idx > last || !args.is_empty() {
+                args.push(tokens[last..idx].join(" "));
+            }
             last = tokens.len();
         } else if idx >= last {
             if !all_alpha_num(&tokens[idx..idx+1]) {
@@ -643,7 +664,7 @@ fn find_def_name(tokens: &[String]) -> Option<(String, Vec<String>)> {
         }
     }
 
-    return Some((name.unwrap(), args));
+    Some((name?, args))
 }
 
 fn find_definition(tokens: &[String]) -> Option<Definition> {
@@ -678,6 +699,9 @@ fn find_definition(tokens: &[String]) -> Option<Definition> {
 fn find_context(tokens: &[String]) -> Option<Vec<Statement>> {
     let mut output: Vec<Statement> = vec![];
     let mut last: usize = 0;
+    if tokens.is_empty() {
+        return Some(output);
+    }
 
     for (idx, token) in tokens.iter().enumerate() {
         if token == "," {
@@ -866,6 +890,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_universe() {
+        let tree = parse(&String::from("\\mathrm{Type}_{2} \\to \\mathrm{Type}_{0}"));
+        assert_eq!(tree, Some(CCExpression::TypeAbs("a".to_string(),
+            Box::new(CCExpression::Universe(2)), Box::new(CCExpression::Sq))));
+        assert_eq!(tree.unwrap().to_latex(), "(\\mathrm{Type}_{2}) \\to \\square");
+        assert_eq!(parse(&String::from("\\mathrm{Type}_{x}")), Some(CCExpression::Var("\\mathrm{Type}_{x}".to_string())));
+    }
+
     #[test]
     fn parse_prim() {
         let tree = parse(&String::from("\\independent"));
@@ -941,6 +974,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_constant_definition() {
+        let def = super::parse_definition("\\vartriangleright bot \\langle \\rangle := \\prod A : \\ast . A : \\ast").unwrap();
+        assert_eq!(def.context, vec![]);
+        assert_eq!(def.args, Vec::<String>::new());
+        assert_eq!(parse("bot \\langle \\rangle"), Some(CCExpression::Def("bot".to_string(), vec![])));
+    }
+
     #[test]
     fn parse_expr_definition() {
         let def1 = "ex \\langle a c, b \\rangle x";
@@ -1027,4 +1068,17 @@ mod tests {
         assert_ne!(tree2, None);
         assert!(tree.unwrap().alpha_equiv(&tree2.unwrap()));
     }
+
+    #[test]
+    fn nested_binder_types() {
+        let expr = "\\lambda f : (\\prod x : A . P x) . \\lambda y : ((A \\to B) \\to C) \\to D . f";
+        if let Some(CCExpression::Abs(_, f_type, ret)) = parse(expr) {
+            assert_eq!(f_type.to_latex(), "\\prod x : A . P x");
+            assert_eq!(ret.to_latex(), "\\lambda y : ((A \\to B) \\to C) \\to D . f");
+        } else {
+            panic!();
+        }
+        let chain = "((A \\to B) \\to (B \\to C) \\to A \\to C) \\to ((C \\to D) \\to (D \\to E) \\to C \\to E) \\to (A \\to B) \\to A";
+        assert_eq!(parse(chain).unwrap().to_latex(), chain);
+    }
 }
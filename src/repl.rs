@@ -0,0 +1,342 @@
+use std::io::{BufRead, Write};
+use crate::model::statement::{Statement};
+use crate::model::expression::{CCExpression};
+use crate::environment::{Environment};
+use crate::parser::{parse, parse_definition, parse_statement};
+use crate::find_term::{search_term};
+use crate::serial::{flagderiv_output};
+use crate::type_check::{check_proof_in};
+use crate::unpack_term::{unpack_term_in};
+use crate::tactic::{Tactic};
+use crate::normalize::{normal_form};
+
+
+const HELP: &str = "\
+:assume x : T     add a statement to the context
+:def DEFINITION   check a definition and add it to the environment
+:check DEFINITION check a definition without adding it
+:find T           search for a term of type T in the current context (also T on its own)
+:proof T          like :find, printing the flagderiv proof
+:infer t          print the type of a term
+:normalize t      print the normal form of a term, unfolding definitions
+:env              print the context and definitions
+:history          print the commands entered so far
+:undo             undo the last :assume or :def, or the last tactic step
+:prove T          start a tactic proof of T in the current context
+:goals            print the proof state and the open goals
+:alts [G]         list the alternatives every strategy offers for goal G (default 1)
+:apply S[/K] [G]  apply alternative K (default 1) of strategy S to goal G
+:auto [G]         solve goal G by proof search
+:qed              check the finished tactic proof and print it
+:abort            abandon the tactic proof
+:quit             leave the REPL";
+
+const NO_TACTIC: &str = "no tactic proof in progress (use :prove)";
+
+struct Snapshot {
+    context: usize,
+    defs: usize,
+    filled: usize,
+    loaded: usize
+}
+
+pub struct Repl {
+    pub env: Environment,
+    pub context: Vec<Statement>,
+    pub history: Vec<String>,
+    pub tactic: Option<Tactic>,
+    snapshots: Vec<Snapshot>
+}
+
+impl Repl {
+    pub fn new(env: Environment) -> Repl {
+        Repl {
+            env,
+            context: vec![],
+            history: vec![],
+            tactic: None,
+            snapshots: vec![]
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            context: self.context.len(),
+            defs: self.env.defs.len(),
+            filled: self.env.filled.len(),
+            loaded: self.env.loaded.len()
+        }
+    }
+
+    fn restore(&mut self, snap: Snapshot) {
+        self.context.truncate(snap.context);
+        self.env.defs.truncate(snap.defs);
+        self.env.derivations.truncate(snap.defs);
+        self.env.filled.truncate(snap.filled);
+        self.env.loaded.truncate(snap.loaded);
+    }
+
+    fn term(&self, text: &str) -> Result<CCExpression, String> {
+        self.declared(&parse(text).ok_or(format!("failed to parse: ({})", text))?)
+    }
+
+    fn declared(&self, ex: &CCExpression) -> Result<CCExpression, String> {
+        let ex = self.env.resolve(ex)?;
+        let mut undeclared: Vec<String> = vec![];
+        for x in ex.free_var() {
+            if !undeclared.contains(&x) && !self.context.iter().any(|st| st.subject.var_str().as_ref() == Some(&x)) {
+                undeclared.push(x);
+            }
+        }
+        if !undeclared.is_empty() {
+            return Err(format!("undeclared variables: {}", undeclared.join(", ")));
+        }
+        Ok(ex)
+    }
+
+    fn type_of(&self, ex: &CCExpression) -> Result<CCExpression, String> {
+        if self.env.spec.is_sort(ex) {
+            return self.env.spec.axiom_of(ex).ok_or(
+                format!("{} has no type", ex.to_latex()));
+        }
+        let lines = unpack_term_in(&self.env.spec, ex, &self.context, &self.env.defs)?;
+        check_proof_in(&self.env.spec, &self.env.defs, &lines)?;
+        Ok(lines.last().unwrap().statement.s_type.clone())
+    }
+
+    fn assume(&mut self, text: &str) -> Result<String, String> {
+        let stmt = parse_statement(text).ok_or(format!("failed to parse statement: ({})", text))?;
+        let name = stmt.subject.var_str().ok_or(format!("not a variable: {}", stmt.subject.to_latex()))?;
+        if self.context.iter().any(|st| st.subject == stmt.subject) {
+            return Err(format!("{} is already in the context", name));
+        }
+        let s_type = self.declared(&stmt.s_type)?;
+        let sort = self.type_of(&s_type)?;
+        if !self.env.spec.is_sort(&sort) {
+            return Err(format!("{} is not a type", s_type.to_latex()));
+        }
+        self.context.push(Statement { subject: stmt.subject, s_type });
+        Ok(Statement::ctx_str(&self.context))
+    }
+
+    fn find(&self, text: &str, flagderiv: bool) -> Result<String, String> {
+        let s_type = self.term(text)?;
+        let (res, _) = search_term(&s_type, &self.context, &self.env.defs, &self.env.search);
+        let proof = res?;
+        if flagderiv { flagderiv_output(&proof) } else { Ok(proof.lines.last().unwrap().to_latex()) }
+    }
+
+    fn show_env(&self) -> String {
+        let mut lines: Vec<String> = vec![format!("context: {}", Statement::ctx_str(&self.context))];
+        for def in &self.env.defs {
+            lines.push(def.to_latex().trim().to_string());
+        }
+        lines.join("\n")
+    }
+
+    fn tactic(&self) -> Result<&Tactic, String> {
+        self.tactic.as_ref().ok_or(NO_TACTIC.to_string())
+    }
+
+    fn tactic_step(&mut self, cmd: &str, rest: &str) -> Result<String, String> {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let goal_arg = |pos: usize| -> Result<usize, String> {
+            match args.get(pos) {
+                Some(x) => x.parse::<usize>().map_err(|_| format!("not a goal number: {}", x)),
+                None => Ok(1)
+            }
+        };
+        let tac = self.tactic.as_mut().ok_or(NO_TACTIC.to_string())?;
+        match cmd {
+            ":apply" => {
+                let spec = args.first().ok_or("usage: :apply STRATEGY[/K] [G]".to_string())?;
+                let (name, alt) = match spec.split_once('/') {
+                    Some((n, k)) => (n, k.parse::<usize>().map_err(|_| format!("not an alternative: {}", k))?),
+                    None => (*spec, 1)
+                };
+                tac.apply(name, alt, goal_arg(1)?, &self.env)?;
+            },
+            _ => { tac.auto(goal_arg(0)?, &self.env)?; }
+        }
+        Ok(tac.show())
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(String::new());
+        }
+        self.history.push(line.to_string());
+        let (cmd, rest) = if line.starts_with(':') {
+            line.split_once(char::is_whitespace).map_or((line, ""), |(c, r)| (c, r.trim()))
+        } else {
+            (":find", line)
+        };
+        match cmd {
+            ":assume" => {
+                let snap = self.snapshot();
+                let res = self.assume(rest);
+                if res.is_ok() { self.snapshots.push(snap); }
+                res
+            },
+            ":def" => {
+                let snap = self.snapshot();
+                match self.env.add_text(rest) {
+                    Ok(()) => {
+                        let added = self.env.defs[snap.defs..].iter().map(
+                            |d| d.name.clone()).collect::<Vec<String>>().join(", ");
+                        self.snapshots.push(snap);
+                        Ok(format!("defined {}", added))
+                    },
+                    Err(msg) => { self.restore(snap); Err(msg) }
+                }
+            },
+            ":check" => {
+                let def = parse_definition(rest).ok_or(format!("failed to parse definition: {}", rest))?;
+                let proof = self.env.check(&def)?;
+                Ok(proof.lines.last().unwrap().to_latex())
+            },
+            ":find" => self.find(rest, false),
+            ":proof" => self.find(rest, true),
+            ":infer" => {
+                let ex = self.term(rest)?;
+                Ok(format!("{} : {}", ex.to_latex(), self.type_of(&ex)?.to_latex()))
+            },
+            ":normalize" => Ok(normal_form(&self.term(rest)?, &self.env.defs)?.to_latex()),
+            ":env" => Ok(self.show_env()),
+            ":history" => Ok(self.history.iter().enumerate().map(
+                |(idx, x)| format!("{:>4} {}", idx + 1, x)).collect::<Vec<String>>().join("\n")),
+            ":prove" => {
+                let s_type = self.term(rest)?;
+                let tac = Tactic::new(&s_type, &self.context);
+                let text = tac.show();
+                self.tactic = Some(tac);
+                Ok(text)
+            },
+            ":goals" => Ok(self.tactic()?.show()),
+            ":alts" => {
+                let idx = if rest.is_empty() { 1 } else {
+                    rest.parse::<usize>().map_err(|_| format!("not a goal number: {}", rest))?
+                };
+                self.tactic()?.alternatives(idx, &self.env)
+            },
+            ":apply" | ":auto" => self.tactic_step(cmd, rest),
+            ":qed" => {
+                let proof = self.tactic()?.qed(&self.env)?;
+                self.tactic = None;
+                Ok(proof.lines.last().unwrap().to_latex())
+            },
+            ":abort" => {
+                self.tactic()?;
+                self.tactic = None;
+                Ok("tactic proof abandoned".to_string())
+            },
+            ":undo" if self.tactic.is_some() => {
+                let tac = self.tactic.as_mut().unwrap();
+                tac.undo()?;
+                Ok(tac.show())
+            },
+            ":undo" => match self.snapshots.pop() {
+                Some(snap) => { self.restore(snap); Ok(self.show_env()) },
+                None => Err("nothing to undo".to_string())
+            },
+            ":help" => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command: {} (try :help)", cmd))
+        }
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) {
+        let _ = write!(output, "> ");
+        let _ = output.flush();
+        for line in input.lines() {
+            let line = match line {
+                Ok(x) => x,
+                Err(_) => { break; }
+            };
+            if line.trim() == ":quit" {
+                break;
+            }
+            match self.execute(&line) {
+                Ok(text) if text.is_empty() => {},
+                Ok(text) => { let _ = writeln!(output, "{}", text); },
+                Err(msg) => { let _ = writeln!(output, "error: {}", msg); }
+            }
+            let _ = write!(output, "> ");
+            let _ = output.flush();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repl_session() {
+        let mut repl = Repl::new(Environment::blank());
+        assert_eq!(repl.execute(":assume A : \\ast").unwrap(), "A : \\ast");
+        assert_eq!(repl.execute(":assume P : A \\to \\ast").unwrap(), "A : \\ast, P : A \\to \\ast");
+        assert_eq!(repl.execute(":assume x : Q").unwrap_err(), "undeclared variables: Q");
+        let mut blank = Repl::new(Environment::blank());
+        assert_eq!(blank.execute(":find A \\to A").unwrap_err(), "undeclared variables: A");
+        assert_eq!(blank.execute(":normalize (\\lambda x : A . x) y").unwrap_err(), "undeclared variables: A, y");
+        assert!(repl.execute(":assume a : a").is_err());
+        repl.execute(":assume h : \\prod x : A . P x").unwrap();
+        repl.execute(":assume a : A").unwrap();
+        assert_eq!(repl.execute("P a").unwrap(),
+                   "A : \\ast, P : A \\to \\ast, h : \\prod x : A . P x, a : A \\vdash h a : P a");
+        assert_eq!(repl.execute(":infer h a").unwrap(), "h a : P a");
+        assert_eq!(repl.execute(":infer \\ast").unwrap(), "\\ast : \\square");
+        assert_eq!(repl.execute(":normalize (\\lambda y : A . y) a").unwrap(), "a");
+        assert!(repl.execute(":normalize (\\lambda x : A . x x) (\\lambda x : A . x x)").is_err());
+
+        assert_eq!(repl.execute(":def B : \\ast \\vartriangleright id \\langle B \\rangle := \\lambda y : B . y : B \\to B").unwrap(),
+                   "defined id");
+        assert!(repl.execute(":check B : \\ast \\vartriangleright bad \\langle B \\rangle := \\lambda y : B . y : B").is_err());
+        assert!(repl.execute(":def B : \\ast \\vartriangleright id \\langle B \\rangle := \\lambda y : B . y : B \\to B").is_err());
+        assert_eq!(repl.execute(":infer id \\langle A \\rangle a").unwrap(), "id \\langle A \\rangle a : A");
+
+        repl.execute(":undo").unwrap();
+        assert_eq!(repl.env.defs.len(), 0);
+        repl.execute(":undo").unwrap();
+        assert_eq!(repl.context.len(), 3);
+        assert_eq!(repl.execute(":frobnicate").unwrap_err(), "unknown command: :frobnicate (try :help)");
+        assert_eq!(repl.history.len(), 18);
+        assert!(repl.execute(":history").unwrap().starts_with("   1 :assume A : \\ast\n"));
+    }
+
+    #[test]
+    fn repl_tactic() {
+        let mut repl = Repl::new(Environment::blank());
+        assert_eq!(repl.execute(":goals").unwrap_err(), "no tactic proof in progress (use :prove)");
+        repl.execute(":assume A : \\ast").unwrap();
+        repl.execute(":assume B : \\ast").unwrap();
+        assert!(repl.execute(":prove (A \\wedge B) \\to B").unwrap().ends_with("goal 1: ?? : (A \\wedge B) \\to B"));
+        assert_eq!(repl.execute(":apply unpack_type_abs/2").unwrap_err(),
+                   "unpack_type_abs has no alternative 2 for ?? : (A \\wedge B) \\to B (1 found)");
+        assert!(repl.execute(":apply unpack_type_abs").unwrap().ends_with("goal 1: ?? : B (ctx: c : A \\wedge B)"));
+        assert!(repl.execute(":alts").unwrap().starts_with("prod_elim/1: sub_{1} : B\n"));
+        assert!(repl.execute(":apply prod_elim/2 x").is_err());
+        assert!(repl.execute(":qed").unwrap_err().ends_with("goals still open"));
+        repl.execute(":undo").unwrap();
+        assert_eq!(repl.context.len(), 2);
+        assert_eq!(repl.execute(":auto").unwrap(), "no open goals (use :qed)");
+        assert_eq!(repl.execute(":qed").unwrap(),
+                   "A : \\ast, B : \\ast \\vdash \\lambda c : A \\wedge B . c B (\\lambda d : A . \\lambda b : B . b) : (A \\wedge B) \\to B");
+        assert!(repl.tactic.is_none());
+        repl.execute(":prove A").unwrap();
+        repl.execute(":abort").unwrap();
+        repl.execute(":undo").unwrap();
+        assert_eq!(repl.context.len(), 1);
+    }
+
+    #[test]
+    fn repl_loop() {
+        let mut repl = Repl::new(Environment::blank());
+        let mut output: Vec<u8> = vec![];
+        repl.run(":assume A : \\ast\nA \\to A\n:undo\n:undo\n:quit\n:env\n".as_bytes(), &mut output);
+        assert_eq!(String::from_utf8(output).unwrap(),
+                   "> A : \\ast\n> A : \\ast \\vdash \\lambda b : A . b : A \\to A\n> context: \n> error: nothing to undo\n> ");
+    }
+}
@@ -2,4 +2,6 @@
 pub mod base;
 pub mod control;
 pub mod proof;
+pub mod stats;
+pub mod trace;
 
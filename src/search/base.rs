@@ -1,8 +1,35 @@
+use std::collections::BTreeMap;
+use super::stats::{StratStats};
 
 pub trait SearchModel<T> {
     fn done(&self, x: &T) -> bool;
     fn finalize(&self, x: T) -> Result<T,String>;
     fn next(&self, x: &T) -> Vec<T>;
     fn weight(&self, x: &T) -> i32;
-}
 
+    // lower bound on the cost of any solution reachable from x,
+    // used by the optimal search (must never overestimate)
+    fn cost(&self, _x: &T) -> i32 {
+        0
+    }
+
+    fn depth(&self, _x: &T) -> u32 {
+        0
+    }
+
+    fn label(&self, _x: &T) -> Option<String> {
+        None
+    }
+
+    fn describe(&self, _x: &T) -> String {
+        String::new()
+    }
+
+    fn summary(&self, _x: &T) -> String {
+        String::new()
+    }
+
+    fn strategy_stats(&self) -> BTreeMap<String, StratStats> {
+        BTreeMap::new()
+    }
+}
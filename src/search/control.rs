@@ -1,39 +1,120 @@
-use std::hash::Hash;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
 use priority_queue::PriorityQueue;
 use super::base::{SearchModel};
+use super::stats::{SearchStats, SearchOutcome};
+use super::trace::{SearchTrace};
 
 
 pub struct SearchControl<T: Hash + Eq> {
-    pub model: Box<dyn SearchModel<T>>
+    pub model: Box<dyn SearchModel<T>>,
+    pub limit: u32,
+    pub timeout: Option<Duration>
 }
 
 impl<T: Hash + Eq> SearchControl<T> {
 
+    #[cfg(test)]
     pub fn search(&self, start: T) -> Result<T, String> {
-        let limit = 1000000;
-        let mut count = 0;
+        self.search_counted(start).0
+    }
+
+    pub fn search_counted(&self, start: T) -> (Result<T, String>, u32) {
+        let (res, stats) = self.search_stats(start, false, 0, false);
+        (res, stats.expansions)
+    }
+
+    #[cfg(test)]
+    pub fn search_optimal(&self, start: T) -> Result<T, String> {
+        self.search_stats(start, true, 0, false).0
+    }
+
+    fn priority(&self, x: &T, optimal: bool) -> (i32, i32) {
+        if optimal {
+            (-self.model.cost(x), self.model.weight(x))
+        } else {
+            (self.model.weight(x), 0)
+        }
+    }
+
+    fn trace_node(&self, trace: &mut Option<SearchTrace>, x: &T) -> usize {
+        match trace {
+            Some(tr) => {
+                let mut hasher = DefaultHasher::new();
+                x.hash(&mut hasher);
+                tr.node(hasher.finish(), self.model.summary(x), self.model.weight(x))
+            },
+            None => 0
+        }
+    }
+
+    fn trace_edge(&self, trace: &mut Option<SearchTrace>, parent: usize, x: &T) -> usize {
+        let child = self.trace_node(trace, x);
+        if let Some(tr) = trace {
+            tr.edge(parent, child, self.model.label(x).unwrap_or_default());
+        }
+        child
+    }
+
+    pub fn search_stats(&self, start: T, optimal: bool, frontier: usize,
+                        record: bool) -> (Result<T, String>, SearchStats) {
+        let t0 = Instant::now();
+        let mut stats = SearchStats::blank();
+        let mut trace = if record { Some(SearchTrace::blank()) } else { None };
         let mut queue = PriorityQueue::new();
-        let w = self.model.weight(&start);
-        queue.push(start, w);
-        while !queue.is_empty() {
-            let (current, _) = queue.pop().unwrap();
+        self.trace_node(&mut trace, &start);
+        let p = self.priority(&start, optimal);
+        queue.push(start, p);
+        let mut outcome = SearchOutcome::Failed;
+        let res = loop {
+            let current = match queue.pop() {
+                Some((x, _)) => x,
+                None => {
+                    outcome = SearchOutcome::Exhausted;
+                    break Err("Exhausted all search options.".to_string());
+                }
+            };
+            let current_id = self.trace_node(&mut trace, &current);
+            if optimal && self.model.done(&current) {
+                if let Some(tr) = &mut trace { tr.mark_solution(current_id); }
+                break self.model.finalize(current);
+            }
+            stats.record_expansion(self.model.depth(&current), self.model.label(&current));
             let mut next = self.model.next(&current);
-            let done = next.iter().enumerate().find(|(_,x)| self.model.done(x));
-            if done.is_none() {
-                for x in next {
-                    let w = self.model.weight(&x);
-                    queue.push(x, w);
+            if !optimal {
+                if let Some(idx) = next.iter().position(|x| self.model.done(x)) {
+                    let done_id = self.trace_edge(&mut trace, current_id, &next[idx]);
+                    if let Some(tr) = &mut trace { tr.mark_solution(done_id); }
+                    break self.model.finalize(next.remove(idx));
                 }
-            } else {
-                return self.model.finalize(next.remove(done.unwrap().0));
             }
-            if count > limit {
-                return Err(format!("reached limit of {}", limit));
-            } else {
-                count += 1;
+            for x in next {
+                self.trace_edge(&mut trace, current_id, &x);
+                let p = self.priority(&x, optimal);
+                queue.push(x, p);
+            }
+            stats.max_queue = stats.max_queue.max(queue.len());
+            if stats.expansions > self.limit {
+                outcome = SearchOutcome::Limit;
+                break Err(format!("reached limit of {}", self.limit));
+            }
+            if let Some(t) = self.timeout.filter(|t| t0.elapsed() > *t) {
+                outcome = SearchOutcome::Timeout;
+                break Err(format!("timed out after {} ms", t.as_millis()));
+            }
+        };
+        stats.elapsed = t0.elapsed();
+        stats.outcome = if res.is_ok() { SearchOutcome::Solved } else { outcome };
+        stats.merge_strategies(&self.model.strategy_stats());
+        stats.trace = trace;
+        while stats.frontier.len() < frontier {
+            match queue.pop() {
+                Some((x, _)) => { stats.frontier.push(self.model.describe(&x)); },
+                None => { break; }
             }
         }
-        return Err("Exhausted all search options.".to_string());
+        (res, stats)
     }
 }
 
@@ -66,16 +147,79 @@ mod tests {
         fn finalize(&self, x: i32) -> Result<i32, String> {
             return Ok(x);
         }
+
+        fn cost(&self, x: &i32) -> i32 {
+            (x*x - self.target).abs()
+        }
     }
 
     #[test]
     fn tokenize_simple() {
         let control = SearchControl {
-            model: Box::new(TestNum { target: 5 })
+            model: Box::new(TestNum { target: 5 }),
+            limit: 1000,
+            timeout: None
         };
         let output = control.search(0);
         assert_eq!(output.unwrap(), 2);
+        let (output2, count) = control.search_counted(0);
+        assert_eq!(output2.unwrap(), 2);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn optimal_simple() {
+        let control = SearchControl {
+            model: Box::new(TestNum { target: 5 }),
+            limit: 1000,
+            timeout: None
+        };
+        let output = control.search_optimal(0);
+        assert_eq!(output.unwrap(), 2);
+    }
+
+    #[test]
+    fn stats_simple() {
+        let control = SearchControl {
+            model: Box::new(TestNum { target: 50 }),
+            limit: 1000,
+            timeout: None
+        };
+        let (output, stats) = control.search_stats(0, false, 2, false);
+        assert_eq!(output.unwrap(), 7);
+        assert_eq!(stats.outcome, SearchOutcome::Solved);
+        assert!(stats.max_queue > 0);
+        assert_eq!(stats.depths[&0], stats.expansions);
+        assert_eq!(stats.frontier.len(), 2);
+        assert_eq!(stats.trace, None);
     }
-}
 
+    #[test]
+    fn trace_simple() {
+        let control = SearchControl {
+            model: Box::new(TestNum { target: 50 }),
+            limit: 1000,
+            timeout: None
+        };
+        let (output, stats) = control.search_stats(0, false, 0, true);
+        assert_eq!(output.unwrap(), 7);
+        let trace = stats.trace.unwrap();
+        assert_eq!(trace.solution[0], 0);
+        assert_eq!(trace.solution.len(), 6);
+        assert!(trace.edges.len() >= stats.expansions as usize);
+        assert!(trace.to_dot().starts_with("digraph search {"));
+    }
 
+    #[test]
+    fn timeout_simple() {
+        let control = SearchControl {
+            model: Box::new(TestNum { target: 1000000 }),
+            limit: 1000,
+            timeout: Some(Duration::ZERO)
+        };
+        let (output, stats) = control.search_stats(0, false, 0, false);
+        assert_eq!(output.unwrap_err(), "timed out after 0 ms");
+        assert_eq!(stats.outcome, SearchOutcome::Timeout);
+        assert_eq!(stats.expansions, 1);
+    }
+}
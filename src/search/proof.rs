@@ -3,6 +3,13 @@ mod subgoal;
 mod finalize;
 mod strategy;
 mod proof_model;
+mod heuristic;
+mod memo;
 
 pub use self::proof_model::ProofSearchModel;
+pub use self::heuristic::{Weights, feature_names};
 
+pub use self::memo::{LemmaMemo};
+pub use self::strategy::{StrategySet};
+pub use self::subgoal::{goal_alternatives};
+pub use self::finalize::{recursive_finalize};
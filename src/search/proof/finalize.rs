@@ -5,6 +5,7 @@ use crate::model::statement::{Statement};
 use crate::model::expression::{CCExpression};
 use crate::model::def::{Definition};
 use crate::model::partial::{Goal, PartialSol};
+use super::memo::{LemmaMemo};
 
 
 fn final_goal_from_subs(inst: &CCExpression, ex: &CCExpression,
@@ -77,7 +78,26 @@ pub fn recursive_finalize(partial: &PartialSol,
     }
     Ok(PartialSol {
         context: partial.context.to_vec(),
-        goals: out_goals.iter().map(|x| x.clone().unwrap()).collect()
+        goals: out_goals.iter().map(|x| x.clone().unwrap()).collect(),
+        strat: partial.strat.clone()
     })
 }
 
+
+fn record_goal(g1: &Goal, context: &[Statement],
+               defs: &[Definition], memo: &mut LemmaMemo) {
+    if let Goal::Unpacked(_, ex, subs, incontext) = g1 {
+        for g2 in subs {
+            record_goal(g2, context, defs, memo);
+        }
+        if let Ok(Goal::Final(lines)) = recursive_finalize_g(g1, context, defs) {
+            memo.record(ex, &[context, incontext].concat(), &lines);
+        }
+    }
+}
+
+pub fn record_solved(partial: &PartialSol, defs: &[Definition], memo: &mut LemmaMemo) {
+    for g in &partial.goals {
+        record_goal(g, &partial.context, defs, memo);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use crate::model::expression::{CCExpression};
+use crate::model::partial::{Goal, PartialSol};
+use super::strategy::{standard_strategy};
+
+
+fn prod_binders(ex: &CCExpression) -> i32 {
+    match ex {
+        CCExpression::TypeAbs(_, a_type, ret) => 1 + prod_binders(a_type) + prod_binders(ret),
+        CCExpression::Abs(_, a_type, ret) => prod_binders(a_type) + prod_binders(ret),
+        CCExpression::Application(lhs, rhs) => prod_binders(lhs) + prod_binders(rhs),
+        CCExpression::Def(_, args) => args.iter().map(prod_binders).sum(),
+        _ => 0
+    }
+}
+
+fn open_goals<'a>(g: &'a Goal, above: &[&'a CCExpression],
+                  output: &mut Vec<(&'a Goal, bool)>) {
+    match g {
+        Goal::Initial(ex, _) => {
+            output.push((g, above.iter().any(|x| x.alpha_equiv(ex))));
+        },
+        Goal::Unpacked(_, ex, lst, _) => {
+            let next_above: Vec<&CCExpression> = [above, &[ex]].concat();
+            for x in lst {
+                open_goals(x, &next_above, output);
+            }
+        },
+        Goal::Final(_) => {}
+    }
+}
+
+pub fn extract_features(x: &PartialSol) -> Vec<(String, i32)> {
+    let c = x.count();
+    let mut open: Vec<(&Goal, bool)> = vec![];
+    for g in &x.goals {
+        open_goals(g, &[], &mut open);
+    }
+    let mut goal_size = 0;
+    let mut binders = 0;
+    let mut ctx_size = 0;
+    let mut repeated = 0;
+    for (g, rep) in &open {
+        if let Goal::Initial(ex, ctx) = g {
+            goal_size += ex.size() as i32;
+            binders += prod_binders(ex);
+            ctx_size += (x.context.len() + ctx.len()) as i32;
+            if *rep { repeated += 1; }
+        }
+    }
+    let mut output: Vec<(String, i32)> = vec![
+        ("bias".to_string(), 1),
+        ("initial".to_string(), c.i as i32),
+        ("unpacked".to_string(), c.u as i32),
+        ("final".to_string(), c.f as i32),
+        ("goal_size".to_string(), goal_size),
+        ("prod_binders".to_string(), binders),
+        ("context_size".to_string(), ctx_size),
+        ("repeated_goals".to_string(), repeated)
+    ];
+    if let Some(name) = &x.strat {
+        output.push((format!("strat_{}", name), 1));
+    }
+    output
+}
+
+pub fn feature_names() -> Vec<String> {
+    let base: Vec<String> = ["bias", "initial", "unpacked", "final", "goal_size",
+        "prod_binders", "context_size", "repeated_goals"].iter().map(
+            |x| x.to_string()).collect();
+    let strats: Vec<String> = standard_strategy().iter().map(
+        |x| format!("strat_{}", x.name())).collect();
+    [base, strats].concat()
+}
+
+pub trait Heuristic {
+    fn weight(&self, x: &PartialSol) -> i32;
+
+    fn features(&self, x: &PartialSol) -> Vec<(String, i32)> {
+        extract_features(x)
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Weights {
+    pub values: BTreeMap<String, i32>
+}
+
+impl Weights {
+    pub fn standard() -> Weights {
+        let mut values: BTreeMap<String, i32> = BTreeMap::new();
+        values.insert("bias".to_string(), 10);
+        values.insert("initial".to_string(), -20);
+        values.insert("unpacked".to_string(), -10);
+        values.insert("final".to_string(), -1);
+        Weights { values }
+    }
+
+    pub fn get(&self, name: &str) -> i32 {
+        *self.values.get(name).unwrap_or(&0)
+    }
+
+    pub fn parse(text: &str) -> Result<Weights, String> {
+        let mut values: BTreeMap<String, i32> = BTreeMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let content = line.split('%').next().unwrap().trim();
+            if content.is_empty() {
+                continue;
+            }
+            let parts: Vec<&str> = content.split_whitespace().collect();
+            if parts.len() != 2 {
+                return Err(format!("line {}: expected \"feature weight\": {}", idx + 1, line));
+            }
+            match parts[1].parse::<i32>() {
+                Ok(w) => { values.insert(parts[0].to_string(), w); },
+                Err(_) => {
+                    return Err(format!("line {}: weight is not an integer: {}", idx + 1, parts[1]));
+                }
+            }
+        }
+        Ok(Weights { values })
+    }
+
+    pub fn load(path: &str) -> Result<Weights, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Weights::parse(&text),
+            Err(e) => Err(format!("failed to read weights ({}): {}", path, e))
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        self.values.iter().map(
+            |(k, v)| format!("{} {}", k, v)
+            ).collect::<Vec<String>>().join("\n")
+    }
+}
+
+impl Heuristic for Weights {
+    fn weight(&self, x: &PartialSol) -> i32 {
+        self.features(x).iter().map(|(name, v)| self.get(name) * v).sum()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_judgement};
+
+    #[test]
+    fn standard_weights_match_goal_count() {
+        let jdg = parse_judgement("A : \\ast \\vdash x : \\prod y : A . A").unwrap();
+        let partial = PartialSol {
+            context: jdg.context.clone(),
+            goals: vec![
+                Goal::Unpacked(CCExpression::Var("sub_{0}".to_string()),
+                jdg.statement.s_type.clone(),
+                vec![Goal::Initial(jdg.statement.s_type.clone(), vec![])],
+                vec![])
+            ],
+            strat: Some("prod_elim".to_string())
+        };
+        let feats = extract_features(&partial);
+        assert!(feats.contains(&("repeated_goals".to_string(), 1)));
+        assert!(feats.contains(&("prod_binders".to_string(), 1)));
+        assert!(feats.contains(&("strat_prod_elim".to_string(), 1)));
+        assert_eq!(Weights::standard().weight(&partial), 10 - 20 - 10);
+    }
+
+    #[test]
+    fn parse_weights() {
+        let w = Weights::parse("% tuned\ninitial -5\n\ngoal_size 2 % per node\n").unwrap();
+        assert_eq!(w.get("initial"), -5);
+        assert_eq!(w.get("goal_size"), 2);
+        assert_eq!(w.get("bias"), 0);
+        assert_eq!(w.to_text(), "goal_size 2\ninitial -5");
+        assert!(Weights::parse("initial x").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use crate::model::statement::{Statement};
+use crate::model::def::{Definition};
+use crate::model::judgement::{Judgement};
+use crate::model::expression::{CCExpression};
+
+
+pub fn goal_key(ex: &CCExpression) -> String {
+    ex.canonical_key()
+}
+
+pub fn defs_key(defs: &[Definition]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for def in defs {
+        def.to_latex().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+pub fn relevant_context(stmt: &Statement, context: &[Statement]) -> Vec<Statement> {
+    let mut needed: Vec<String> = [stmt.subject.free_var(), stmt.s_type.free_var()].concat();
+    let mut keep: Vec<bool> = vec![false; context.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (idx, c) in context.iter().enumerate() {
+            if keep[idx] {
+                continue;
+            }
+            if let Some(name) = c.subject.var_str() {
+                if needed.contains(&name) {
+                    keep[idx] = true;
+                    needed.extend(c.s_type.free_var());
+                    changed = true;
+                }
+            }
+        }
+    }
+    context.iter().zip(keep).filter(|(_, k)| *k).map(|(c, _)| c.clone()).collect()
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct MemoEntry {
+    pub context: Vec<Statement>,
+    pub lines: Vec<Judgement>
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct LemmaMemo {
+    pub solved: HashMap<String, Vec<MemoEntry>>,
+    pub failed: HashSet<String>,
+    pub hits: u32,
+    pub misses: u32,
+    defs: u64
+}
+
+impl LemmaMemo {
+    pub fn blank() -> LemmaMemo {
+        LemmaMemo {
+            solved: HashMap::new(),
+            failed: HashSet::new(),
+            hits: 0,
+            misses: 0,
+            defs: defs_key(&[])
+        }
+    }
+
+    // entries are only reused with the definitions they were found with
+    pub fn use_defs(&mut self, defs: &[Definition]) {
+        self.defs = defs_key(defs);
+    }
+
+    fn solved_key(&self, ex: &CCExpression) -> String {
+        format!("{:016x} | {}", self.defs, goal_key(ex))
+    }
+
+    fn failure_key(&self, ex: &CCExpression, context: &[Statement], known: &[Statement]) -> String {
+        format!("{} | {} | {}", self.solved_key(ex), Statement::ctx_str(context), Statement::ctx_str(known))
+    }
+
+    pub fn lookup(&mut self, ex: &CCExpression, context: &[Statement]) -> Option<Vec<Judgement>> {
+        let found = self.solved.get(&self.solved_key(ex)).and_then(
+            |lst| lst.iter().find(|e| Statement::weaker_eq(context, &e.context))
+            ).map(|e| e.lines.to_vec());
+        if found.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        found
+    }
+
+    pub fn record(&mut self, ex: &CCExpression, context: &[Statement], lines: &[Judgement]) {
+        let last = match lines.last() {
+            Some(x) => x,
+            None => { return; }
+        };
+        let relevant = relevant_context(&last.statement, context);
+        let key = self.solved_key(ex);
+        let entries = self.solved.entry(key).or_default();
+        if entries.iter().any(|e| Statement::weaker_eq(&relevant, &e.context)) {
+            return;
+        }
+        entries.push(MemoEntry { context: relevant, lines: lines.to_vec() });
+    }
+
+    pub fn has_failed(&self, ex: &CCExpression, context: &[Statement], known: &[Statement]) -> bool {
+        self.failed.contains(&self.failure_key(ex, context, known))
+    }
+
+    pub fn record_failure(&mut self, ex: &CCExpression, context: &[Statement], known: &[Statement]) {
+        let key = self.failure_key(ex, context, known);
+        self.failed.insert(key);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_judgement, parse, parse_definition};
+
+    #[test]
+    fn alpha_equivalent_keys() {
+        let t1 = parse("\\prod x : \\ast . x \\to x").unwrap();
+        let t2 = parse("\\prod y : \\ast . y \\to y").unwrap();
+        let t3 = parse("\\prod y : \\ast . y \\to A").unwrap();
+        assert_eq!(goal_key(&t1), goal_key(&t2));
+        assert_ne!(goal_key(&t1), goal_key(&t3));
+    }
+
+    #[test]
+    fn reuse_in_larger_context() {
+        let jdg = parse_judgement("A : \\ast, B : \\ast, x : A \\vdash x : A").unwrap();
+        let mut memo = LemmaMemo::blank();
+        memo.record(&jdg.statement.s_type, &jdg.context, std::slice::from_ref(&jdg));
+        assert_eq!(memo.solved[&memo.solved_key(&jdg.statement.s_type)][0].context,
+                   vec![jdg.context[0].clone(), jdg.context[2].clone()]);
+
+        let bigger = parse_judgement("A : \\ast, x : A, C : \\ast \\vdash y : A").unwrap();
+        assert_eq!(memo.lookup(&bigger.statement.s_type, &bigger.context), Some(vec![jdg.clone()]));
+        let smaller = parse_judgement("A : \\ast \\vdash y : A").unwrap();
+        assert_eq!(memo.lookup(&smaller.statement.s_type, &smaller.context), None);
+        assert_eq!((memo.hits, memo.misses), (1, 1));
+
+        memo.record_failure(&jdg.statement.s_type, &smaller.context, &[]);
+        assert!(memo.has_failed(&jdg.statement.s_type, &smaller.context, &[]));
+        assert!(!memo.has_failed(&jdg.statement.s_type, &jdg.context, &[]));
+    }
+
+    #[test]
+    fn keyed_by_definitions() {
+        let jdg = parse_judgement("A : \\ast, x : A \\vdash x : A").unwrap();
+        let defs = vec![parse_definition("A : \\ast \\vartriangleright id \\langle A \\rangle := \\lambda x : A . x : A \\to A").unwrap()];
+        let mut memo = LemmaMemo::blank();
+        memo.use_defs(&defs);
+        memo.record(&jdg.statement.s_type, &jdg.context, std::slice::from_ref(&jdg));
+        memo.record_failure(&jdg.context[0].s_type, &jdg.context, &[]);
+        assert!(memo.lookup(&jdg.statement.s_type, &jdg.context).is_some());
+
+        memo.use_defs(&[]);
+        assert_eq!(memo.lookup(&jdg.statement.s_type, &jdg.context), None);
+        assert!(!memo.has_failed(&jdg.context[0].s_type, &jdg.context, &[]));
+
+        memo.use_defs(&defs);
+        assert!(memo.has_failed(&jdg.context[0].s_type, &jdg.context, &[]));
+    }
+}
@@ -1,13 +1,35 @@
-
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::collections::BTreeMap;
 use crate::model::def::{Definition};
 use crate::model::partial::{PartialSol};
 use crate::search::base::{SearchModel};
+use crate::search::stats::{StratStats};
 
 use super::subgoal::{next_sol_from_sol};
-use super::finalize::{recursive_finalize};
+use super::finalize::{recursive_finalize, record_solved};
+use super::memo::{LemmaMemo};
+use super::strategy::{StrategySet};
+use super::heuristic::{Heuristic};
 
 pub struct ProofSearchModel {
-    pub defs: Vec<Definition>
+    pub defs: Vec<Definition>,
+    pub heuristic: Box<dyn Heuristic>,
+    pub strategies: Rc<StrategySet>,
+    pub timing: RefCell<BTreeMap<String, StratStats>>,
+    pub memo: Option<Rc<RefCell<LemmaMemo>>>
+}
+
+impl ProofSearchModel {
+    pub fn new(defs: &[Definition], heuristic: Box<dyn Heuristic>) -> ProofSearchModel {
+        ProofSearchModel {
+            defs: defs.to_vec(),
+            heuristic,
+            strategies: Rc::new(StrategySet::standard()),
+            timing: RefCell::new(BTreeMap::new()),
+            memo: None
+        }
+    }
 }
 
 
@@ -17,7 +39,10 @@ impl SearchModel<PartialSol> for ProofSearchModel {
         }
 
         fn next(&self, x: &PartialSol) -> Vec<PartialSol> {
-            let res = next_sol_from_sol(x, &self.defs);
+            let mut memo = self.memo.as_ref().map(|m| m.borrow_mut());
+            let res = next_sol_from_sol(x, &self.defs, &self.strategies,
+                                        &mut self.timing.borrow_mut(),
+                                        memo.as_deref_mut());
             if let Ok(x) = res {
                 return x;
             } else {
@@ -26,14 +51,39 @@ impl SearchModel<PartialSol> for ProofSearchModel {
         }
 
         fn weight(&self, x: &PartialSol) -> i32 {
-            let c = x.count();
-            let w = (c.i*20 + c.u*10 + c.f) as i32;
-            return 10 - w
+            self.heuristic.weight(x)
         }
 
         fn finalize(&self, x: PartialSol) -> Result<PartialSol, String> {
+            if let Some(m) = &self.memo {
+                record_solved(&x, &self.defs, &mut m.borrow_mut());
+            }
             return recursive_finalize(&x, &self.defs);
         }
-}
 
+        fn cost(&self, x: &PartialSol) -> i32 {
+            x.min_size() as i32
+        }
+
+        fn depth(&self, x: &PartialSol) -> u32 {
+            let c = x.count();
+            c.u + c.f
+        }
+
+        fn label(&self, x: &PartialSol) -> Option<String> {
+            x.strat.clone()
+        }
+
+        fn describe(&self, x: &PartialSol) -> String {
+            x.to_latex()
+        }
+
+        fn summary(&self, x: &PartialSol) -> String {
+            let c = x.count();
+            format!("i={} u={} f={}", c.i, c.u, c.f)
+        }
 
+        fn strategy_stats(&self) -> BTreeMap<String, StratStats> {
+            return self.timing.borrow().clone();
+        }
+}
@@ -9,5 +9,5 @@ mod def_known;
 mod stratset;
 
 pub use self::base::{ProofStrat};
-pub use self::stratset::{standard_strategy};
+pub use self::stratset::{standard_strategy, StrategySet};
 
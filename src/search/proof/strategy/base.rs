@@ -13,6 +13,8 @@ pub trait ProofStrat {
                  concs: &[Judgement],
                  defs: &[Definition]) -> Result<Vec<Goal>, String>;
 
+    fn name(&self) -> String;
+
     fn full_context(&self,
                     context: &[Statement],
                     inner_context: &[Statement],
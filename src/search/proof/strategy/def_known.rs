@@ -1,38 +1,38 @@
 
 use crate::model::statement::{Statement};
 use crate::model::judgement::{Judgement};
-use crate::model::expression::{CCExpression};
+use crate::model::expression::{CCExpression, fresh_name};
 use crate::model::def::{Definition};
+use std::collections::BTreeMap;
 use crate::model::partial::{Goal};
+use crate::model::rules::{do_type_sub};
 use super::base::{ProofStrat};
 
 pub struct DefKnown {}
 
 fn matching_args(context: &[Statement],
                  decided: &[Statement],
-                 undecided: &[Statement]) -> Vec<Vec<Statement>> {
-    if undecided.len() == 0 {
-        vec![decided.to_vec()]
-    } else {
-        context.iter().filter(
-            |stmt| stmt.s_type == undecided[0].s_type
-        ).map(|stmt| {
-            let new_decide: Vec<Statement> = [decided, &[stmt.clone()]].concat();
-            let new_undecide: Vec<Statement> = undecided[1..].iter().map(
-                |stmt1| Statement {
-                    subject: stmt1.subject.clone(),
-                    s_type: stmt1.s_type.substitute(
-                        undecided[0].subject.var_str().as_ref().unwrap(),
-                        &stmt.subject)
-                }).collect();
-            matching_args(context, &new_decide, &new_undecide)
-        }).flatten().collect()
+                 args: &[Statement]) -> Vec<Vec<Statement>> {
+    if decided.len() == args.len() {
+        return vec![decided.to_vec()];
     }
+    let known: Vec<(String, CCExpression)> = args.iter().zip(decided).map(
+        |(arg, stmt)| (arg.subject.var_str().unwrap(), stmt.subject.clone())).collect();
+    let expected = args[decided.len()].s_type.instantiate_all(&known);
+    context.iter().filter(
+        |stmt| stmt.s_type == expected
+    ).flat_map(|stmt| {
+        let new_decide: Vec<Statement> = [decided, std::slice::from_ref(stmt)].concat();
+        matching_args(context, &new_decide, args)
+    }).collect()
 }
 
 fn match_single_def(def: &Definition,
                     context: &[Statement]) -> Vec<Vec<Statement>> {
-    return matching_args(context, &[], &def.arg_statements());
+    match def.arg_statements() {
+        Some(args) => matching_args(context, &[], &args),
+        None => vec![]
+    }
 }
 
 fn find_matches(defs: &[Definition],
@@ -50,10 +50,7 @@ fn type_already_known(new_type: &CCExpression,
 }
 
 fn make_new_type(def: &Definition, subs: &[Statement]) -> CCExpression {
-    let mut new_type = def.body.s_type.clone();
-    subs.iter().zip(&def.args).for_each(
-        |(sub, arg)| { new_type = new_type.substitute(arg, &sub.subject); });
-    new_type
+    do_type_sub(&def.body.s_type, def, &subs.to_vec())
 }
 
 
@@ -85,6 +82,88 @@ fn make_goal(ex: &CCExpression,
         )
 }
 
+fn unify(pattern: &CCExpression, target: &CCExpression,
+         vars: &[String], bound: &[String],
+         subst: &mut BTreeMap<String, CCExpression>) -> bool {
+    match (pattern, target) {
+        (CCExpression::Var(x), _) if vars.contains(x) => {
+            if let Some(known) = subst.get(x) {
+                return known.alpha_equiv(target);
+            }
+            if target.free_var().iter().any(|v| bound.contains(v)) {
+                return false;
+            }
+            subst.insert(x.clone(), target.clone());
+            true
+        },
+        (CCExpression::Var(x), CCExpression::Var(y)) => x == y,
+        (CCExpression::Star, CCExpression::Star) => true,
+        (CCExpression::Sq, CCExpression::Sq) => true,
+        (CCExpression::Universe(i), CCExpression::Universe(j)) => i == j,
+        (CCExpression::Prim, CCExpression::Prim) => true,
+        (CCExpression::Application(l1, r1), CCExpression::Application(l2, r2)) => {
+            unify(l1, l2, vars, bound, subst) && unify(r1, r2, vars, bound, subst)
+        },
+        (CCExpression::Def(n1, a1), CCExpression::Def(n2, a2)) => {
+            n1 == n2 && a1.len() == a2.len() &&
+                a1.iter().zip(a2).all(|(x, y)| unify(x, y, vars, bound, subst))
+        },
+        (CCExpression::Abs(x, t1, r1), CCExpression::Abs(y, t2, r2)) |
+        (CCExpression::TypeAbs(x, t1, r1), CCExpression::TypeAbs(y, t2, r2)) => {
+            if !unify(t1, t2, vars, bound, subst) {
+                return false;
+            }
+            let inner_vars: Vec<String> = vars.iter().filter(|v| *v != x).cloned().collect();
+            if x == y {
+                return unify(r1, r2, &inner_vars, &[bound, std::slice::from_ref(x)].concat(), subst);
+            }
+            let fresh = fresh_name(x, &[pattern.names(), target.names(), vars.to_vec(), bound.to_vec()].concat());
+            let new_r1 = r1.instantiate(x, &CCExpression::Var(fresh.clone()));
+            let new_r2 = r2.instantiate(y, &CCExpression::Var(fresh.clone()));
+            unify(&new_r1, &new_r2, &inner_vars, &[bound, &[fresh]].concat(), subst)
+        },
+        (_, _) => false
+    }
+}
+
+fn backward_goal(ex: &CCExpression,
+                 context: &[Statement],
+                 inner_context: &[Statement],
+                 def: &Definition) -> Option<Goal> {
+    let mut subst: BTreeMap<String, CCExpression> = BTreeMap::new();
+    if !unify(&def.body.s_type, ex, &def.args, &[], &mut subst) {
+        return None;
+    }
+    let arg_types = def.type_list()?;
+    let mut values: Vec<(String, CCExpression)> = vec![];
+    let mut open: Vec<usize> = vec![];
+    for (idx, (arg, a_type)) in def.args.iter().zip(&arg_types).enumerate() {
+        if let Some(value) = subst.get(arg) {
+            values.push((arg.clone(), value.clone()));
+            continue;
+        }
+        let used_later = arg_types[idx + 1..].iter().chain(std::iter::once(&def.body.s_type)).any(
+            |t| t.free_var().contains(arg));
+        if a_type.is_sort() || used_later {
+            return None;
+        }
+        values.push((arg.clone(), CCExpression::Var(format!("sub_{{{}}}", open.len()))));
+        open.push(idx);
+    }
+    let subs: Vec<Goal> = open.iter().map(
+        |idx| Goal::Initial(arg_types[*idx].instantiate_all(&values), inner_context.to_vec())).collect();
+    let inst_args: Vec<CCExpression> = values.into_iter().map(|(_, value)| value).collect();
+    let inst = CCExpression::Def(def.name.to_string(), inst_args);
+    if subs.is_empty() {
+        return Some(Goal::Final(vec![Judgement {
+            defs: vec![],
+            context: [context, inner_context].concat(),
+            statement: Statement { subject: inst, s_type: ex.clone() }
+        }]));
+    }
+    Some(Goal::Unpacked(inst, ex.clone(), subs, inner_context.to_vec()))
+}
+
 impl ProofStrat for DefKnown {
     fn sub_goals(&self, ex: &CCExpression,
                  context: &[Statement],
@@ -97,11 +176,15 @@ impl ProofStrat for DefKnown {
         let all_known: Vec<Statement> = [full_context, usable_conc].concat();
         let matches = find_matches(&defs, &all_known);
 
-        let goals: Vec<Goal> = matches.iter().filter(
+        let forward: Vec<Goal> = matches.iter().filter(
             |(def, subs)| !type_already_known(&make_new_type(&def, &subs), &all_known)
             ).map(
             |(def, subs)| make_goal(ex, context, inner_context, def, subs)
             ).collect();
+        let backward: Vec<Goal> = defs.iter().filter_map(
+            |def| backward_goal(ex, context, inner_context, def)
+            ).collect();
+        let goals: Vec<Goal> = [forward, backward].concat();
 
         if goals.len() > 0 {
             Ok(goals)
@@ -109,12 +192,16 @@ impl ProofStrat for DefKnown {
             Err("No appropriate products to instantiate".to_string())
         }
     }
+
+    fn name(&self) -> String {
+        String::from("def_known")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::{parse_judgement, parse_definition};
+    use crate::parser::{parse, parse_judgement, parse_definition};
 
     #[test]
     fn test_def_known_strat() {
@@ -148,4 +235,57 @@ mod tests {
             Err(_) => { panic!(); }
         }
     }
+
+    #[test]
+    fn test_def_backward_goals() {
+        let jdg: Judgement = parse_judgement("P : \\ast, Q : \\ast \\vdash y : P \\wedge Q").unwrap();
+        let def = parse_definition("A : \\ast, B : \\ast, a : A, b : B \\vartriangleright pair \\langle A, B, a, b \\rangle := \\independent : A \\wedge B").unwrap();
+        let strat = DefKnown {};
+        let res = strat.sub_goals(&jdg.statement.s_type, &jdg.context, &[], &[], &[def]).unwrap();
+        assert_eq!(res.len(), 1);
+        if let Goal::Unpacked(inst, _, subs, _) = &res[0] {
+            assert_eq!(inst.to_latex(), "pair \\langle P, Q, sub_{0}, sub_{1} \\rangle");
+            assert_eq!(subs, &vec![
+                Goal::Initial(jdg.context[0].subject.clone(), vec![]),
+                Goal::Initial(jdg.context[1].subject.clone(), vec![])
+            ]);
+        } else { panic!(); }
+
+        let other = parse_judgement("P : \\ast, Q : \\ast \\vdash y : P \\vee Q").unwrap();
+        let def2 = parse_definition("A : \\ast, B : \\ast, a : A, b : B \\vartriangleright pair \\langle A, B, a, b \\rangle := \\independent : A \\wedge B").unwrap();
+        assert!(strat.sub_goals(&other.statement.s_type, &other.context, &[], &[], &[def2]).is_err());
+    }
+
+    #[test]
+    fn swapped_arguments() {
+        let jdg: Judgement = parse_judgement("A : \\ast, B : \\ast \\vdash y : A \\to B").unwrap();
+        let def = parse_definition("A : \\ast, B : \\ast, a : A \\vartriangleright f \\langle A, B, a \\rangle := \\independent : B \\to A").unwrap();
+        let goal = backward_goal(&jdg.statement.s_type, &jdg.context, &[], &def).unwrap();
+        if let Goal::Unpacked(inst, _, subs, _) = &goal {
+            assert_eq!(inst.to_latex(), "f \\langle B, A, sub_{0} \\rangle");
+            assert_eq!(subs, &vec![Goal::Initial(jdg.context[1].subject.clone(), vec![])]);
+        } else { panic!(); }
+
+        let swap = parse_definition("A : \\ast, B : \\ast, a : A, b : B \\vartriangleright g \\langle A, B, a, b \\rangle := \\independent : B").unwrap();
+        let ctx = parse_judgement("A : \\ast, B : \\ast, x : B, z : A \\vdash y : A").unwrap().context;
+        let swapped: Vec<Vec<String>> = match_single_def(&swap, &ctx).iter().map(
+            |subs| subs.iter().map(|x| x.subject.to_latex()).collect::<Vec<String>>()
+            ).filter(|names| names[0] == "B" && names[1] == "A").collect();
+        assert_eq!(swapped, vec![vec!["B", "A", "x", "z"]]);
+        let subs: Vec<Statement> = ["B", "A", "x", "z"].iter().map(
+            |name| ctx.iter().find(|x| x.subject.to_latex() == *name).unwrap().clone()).collect();
+        assert_eq!(make_new_type(&swap, &subs).to_latex(), "A");
+    }
+
+    #[test]
+    fn unify_renames_binders() {
+        let pattern = parse("\\prod x : \\ast . B").unwrap();
+        let target = parse("\\prod y : \\ast . x").unwrap();
+        let mut subst: BTreeMap<String, CCExpression> = BTreeMap::new();
+        assert!(unify(&pattern, &target, &["B".to_string()], &[], &mut subst));
+        assert_eq!(subst["B"].to_latex(), "x");
+
+        let dependent = parse("\\prod y : \\ast . y").unwrap();
+        assert!(!unify(&pattern, &dependent, &["B".to_string()], &[], &mut BTreeMap::new()));
+    }
 }
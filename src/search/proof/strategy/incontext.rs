@@ -12,10 +12,12 @@ impl ProofStrat for InContext {
     fn sub_goals(&self, ex: &CCExpression,
                  context: &[Statement],
                  inner_context: &[Statement],
-                 _: &[Judgement],
+                 concs: &[Judgement],
                  _: &[Definition]) -> Result<Vec<Goal>, String> {
+        let full_context: Vec<Statement> = self.full_context(context, inner_context);
+        let usable_conc: Vec<Statement> = self.usable_conc(&full_context, concs);
 
-        let output: Vec<Result<Goal, String>> = context.iter().chain(inner_context).filter_map(
+        let output: Vec<Result<Goal, String>> = full_context.iter().chain(&usable_conc).filter_map(
             |stmt| if stmt.s_type == *ex {
                 Some(stmt.clone())
             } else {
@@ -38,6 +40,10 @@ impl ProofStrat for InContext {
             Err(format!("not in context: {}", ex.to_latex()))
         }
     }
+
+    fn name(&self) -> String {
+        String::from("in_context")
+    }
 }
 
 
@@ -64,5 +70,19 @@ mod tests {
             Err(_) => { panic!(); }
         }
     }
-}
 
+    #[test]
+    fn in_context_uses_conclusions() {
+        let jdg: Judgement = parse_judgement("A:\\ast, P:A \\to \\ast, h:\\prod x:A.P x, a:A \\vdash y : P a").unwrap();
+        let conc: Judgement = parse_judgement("A:\\ast, P:A \\to \\ast, h:\\prod x:A.P x, a:A \\vdash h a : P a").unwrap();
+        let strat = InContext {};
+        let ex = &jdg.statement.s_type;
+        assert!(strat.sub_goals(ex, &jdg.context, &[], &[], &[]).is_err());
+
+        let lst = strat.sub_goals(ex, &jdg.context, &[], &[conc], &[]).unwrap();
+        assert_eq!(lst.len(), 1);
+        if let Goal::Final(jdgs) = &lst[0] {
+            assert_eq!(jdgs.last().unwrap().statement.to_latex(), "h a : P a");
+        } else { panic!(); }
+    }
+}
@@ -77,6 +77,10 @@ impl ProofStrat for KnownArrow {
             Err(format!("failed to find arrow matching: {}", ex.to_latex()))
         }
     }
+
+    fn name(&self) -> String {
+        String::from("known_arrow")
+    }
 }
 
 #[cfg(test)]
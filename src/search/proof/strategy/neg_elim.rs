@@ -74,6 +74,10 @@ impl ProofStrat for NegElim {
             None => Err("No appropriate negations to apply".to_string())
         }
     }
+
+    fn name(&self) -> String {
+        String::from("neg_elim")
+    }
 }
 
 #[cfg(test)]
@@ -100,6 +100,10 @@ impl ProofStrat for ProdElim {
             Err("No appropriate products to instantiate".to_string())
         }
     }
+
+    fn name(&self) -> String {
+        String::from("prod_elim")
+    }
 }
 
 #[cfg(test)]
@@ -1,4 +1,3 @@
-
 use super::untypeabs::{UnpackTypeAbs};
 use super::incontext::{InContext};
 use super::known_arrow::{KnownArrow};
@@ -19,3 +18,112 @@ pub fn standard_strategy() -> Vec<Box<dyn ProofStrat>> {
     ];
 }
 
+pub struct StrategyEntry {
+    pub strat: Box<dyn ProofStrat>,
+    pub priority: i32,
+    pub enabled: bool
+}
+
+pub struct StrategySet {
+    pub entries: Vec<StrategyEntry>
+}
+
+impl StrategySet {
+    pub fn standard() -> StrategySet {
+        let mut set = StrategySet { entries: vec![] };
+        for (idx, strat) in standard_strategy().into_iter().enumerate() {
+            set.register(strat, idx as i32);
+        }
+        set
+    }
+
+    pub fn register(&mut self, strat: Box<dyn ProofStrat>, priority: i32) {
+        let name = strat.name();
+        self.entries.retain(|e| e.strat.name() != name);
+        self.entries.push(StrategyEntry { strat, priority, enabled: true });
+    }
+
+    fn entry_mut(&mut self, name: &str) -> Result<&mut StrategyEntry, String> {
+        let known = self.names().join(", ");
+        match self.entries.iter_mut().find(|e| e.strat.name() == name) {
+            Some(e) => Ok(e),
+            None => Err(format!("unknown strategy: {} (known: {})", name, known))
+        }
+    }
+
+    pub fn enable(&mut self, name: &str) -> Result<(), String> {
+        self.entry_mut(name)?.enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self, name: &str) -> Result<(), String> {
+        self.entry_mut(name)?.enabled = false;
+        Ok(())
+    }
+
+    pub fn set_priority(&mut self, name: &str, priority: i32) -> Result<(), String> {
+        self.entry_mut(name)?.priority = priority;
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.strat.name()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<&dyn ProofStrat, String> {
+        match self.entries.iter().find(|e| e.strat.name() == name) {
+            Some(e) => Ok(e.strat.as_ref()),
+            None => Err(format!("unknown strategy: {} (known: {})", name, self.names().join(", ")))
+        }
+    }
+
+    pub fn active(&self) -> Vec<&dyn ProofStrat> {
+        let mut enabled: Vec<&StrategyEntry> = self.entries.iter().filter(|e| e.enabled).collect();
+        enabled.sort_by_key(|e| e.priority);
+        enabled.iter().map(|e| e.strat.as_ref()).collect()
+    }
+
+    pub fn configure(&mut self, spec: &str) -> Result<(), String> {
+        for item in spec.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            if let Some(name) = item.strip_prefix('-') {
+                self.disable(name)?;
+            } else if let Some((name, p)) = item.split_once('=') {
+                match p.parse::<i32>() {
+                    Ok(priority) => {
+                        self.enable(name)?;
+                        self.set_priority(name, priority)?;
+                    },
+                    Err(_) => { return Err(format!("priority is not an integer: {}", item)); }
+                }
+            } else {
+                self.enable(item.strip_prefix('+').unwrap_or(item))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configure_strategies() {
+        let mut set = StrategySet::standard();
+        let names = |s: &StrategySet| s.active().iter().map(|x| x.name()).collect::<Vec<String>>();
+        assert_eq!(names(&set), standard_strategy().iter().map(|x| x.name()).collect::<Vec<String>>());
+
+        set.configure("-def_known, unpack_type_abs=-1, -neg_elim").unwrap();
+        assert_eq!(names(&set), ["unpack_type_abs", "in_context", "known_arrow", "prod_elim"]);
+        set.configure("+neg_elim").unwrap();
+        assert_eq!(names(&set).len(), 5);
+
+        assert!(set.configure("magic").is_err());
+        assert!(set.configure("in_context=x").is_err());
+
+        set.register(Box::new(InContext {}), 10);
+        assert_eq!(names(&set).last().unwrap(), "in_context");
+        assert_eq!(set.entries.len(), 6);
+    }
+}
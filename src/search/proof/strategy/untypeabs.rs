@@ -40,6 +40,10 @@ impl ProofStrat for UnpackTypeAbs {
             Err(format!("Not a type abstraction: {}", ex.to_latex()))
         }
     }
+
+    fn name(&self) -> String {
+        String::from("unpack_type_abs")
+    }
 }
 
 
@@ -4,24 +4,33 @@ use crate::model::judgement::{Judgement};
 use crate::model::expression::{CCExpression};
 use crate::model::def::{Definition};
 use crate::model::partial::{PartialSol, Goal, WithConc};
-use super::strategy::{standard_strategy};
+use crate::search::stats::{StratStats};
+use super::strategy::{StrategySet};
+use super::memo::{LemmaMemo};
+use std::collections::BTreeMap;
+use std::time::Instant;
 
 
 fn sub_goals_from_expression(ex: &CCExpression,
                              context: &[Statement],
                              inner_context: &[Statement],
                              concs: &[Judgement],
-                             defs: &[Definition]) -> Result<Vec<Goal>, String> {
+                             defs: &[Definition],
+                             strategies: &StrategySet,
+                             timing: &mut BTreeMap<String, StratStats>) -> Result<Vec<(String, Goal)>, String> {
 
-    let strategies = standard_strategy();
-
-    let output: Vec<Goal> = strategies.iter().map(
-        |strat| strat.sub_goals(ex, context, inner_context, concs, defs)
-        ).filter_map(
-            |block| match block {
-                Ok(lst) => Some(lst),
-                Err(_) => None
-            }).flatten().collect();
+    let mut output: Vec<(String, Goal)> = vec![];
+    for strat in strategies.active() {
+        let t0 = Instant::now();
+        let res = strat.sub_goals(ex, context, inner_context, concs, defs);
+        let entry = timing.entry(strat.name()).or_default();
+        entry.calls += 1;
+        entry.time += t0.elapsed();
+        if let Ok(lst) = res {
+            entry.produced += lst.len() as u32;
+            output.extend(lst.into_iter().map(|g| (strat.name(), g)));
+        }
+    }
     if output.len() > 0 {
         Ok(output)
     } else {
@@ -30,10 +39,35 @@ fn sub_goals_from_expression(ex: &CCExpression,
 }
 
 fn unpack_goal(g1: &WithConc, context: &[Statement],
-               defs: &[Definition]) -> Result<(Goal, Vec<Goal>), String> {
+               defs: &[Definition],
+               strategies: &StrategySet,
+               timing: &mut BTreeMap<String, StratStats>,
+               memo: &mut Option<&mut LemmaMemo>) -> Result<(Goal, Vec<(String, Goal)>), String> {
     match &g1.goal {
         Goal::Initial(ex, ctx) => {
-            let subs = sub_goals_from_expression(&ex, context, &ctx, &g1.conc, defs);
+            let full_context: Vec<Statement> = [context, ctx].concat();
+            let known: Vec<Statement> = g1.conc.iter().map(|j| j.statement.clone()).collect();
+            if let Some(m) = memo {
+                if let Some(lines) = m.lookup(ex, &full_context) {
+                    return Ok((g1.goal.clone(), vec![("memo".to_string(), Goal::Final(lines))]));
+                }
+                if m.has_failed(ex, &full_context, &known) {
+                    return Err(format!("known failure for {}", ex.to_latex()));
+                }
+            }
+            let subs = sub_goals_from_expression(ex, context, ctx, &g1.conc, defs, strategies, timing);
+            if let Some(m) = memo {
+                match &subs {
+                    Ok(lst) => {
+                        let solved = lst.iter().find_map(
+                            |(_, g)| if let Goal::Final(lines) = g { Some(lines) } else { None });
+                        if let Some(lines) = solved {
+                            m.record(ex, &full_context, lines);
+                        }
+                    },
+                    Err(_) => { m.record_failure(ex, &full_context, &known); }
+                }
+            }
             match subs {
                 Ok(lst) => Ok((g1.goal.clone(), lst)),
                 Err(x) => Err(x)
@@ -43,21 +77,46 @@ fn unpack_goal(g1: &WithConc, context: &[Statement],
     }
 }
 
+pub fn goal_alternatives(g1: &WithConc, context: &[Statement],
+                         defs: &[Definition],
+                         strategies: &StrategySet,
+                         name: Option<&str>) -> Result<Vec<(String, Goal)>, String> {
+    match &g1.goal {
+        Goal::Initial(ex, ctx) => {
+            if let Some(n) = name {
+                let strat = strategies.get(n)?;
+                let lst = strat.sub_goals(ex, context, ctx, &g1.conc, defs)?;
+                return Ok(lst.into_iter().map(|g| (strat.name(), g)).collect());
+            }
+            let mut output: Vec<(String, Goal)> = vec![];
+            for strat in strategies.active() {
+                if let Ok(lst) = strat.sub_goals(ex, context, ctx, &g1.conc, defs) {
+                    output.extend(lst.into_iter().map(|g| (strat.name(), g)));
+                }
+            }
+            Ok(output)
+        },
+        _ => Err(format!("Can only unpack initial, not {}", g1.goal.to_latex()))
+    }
+}
+
 pub fn next_sol_from_sol(partial: &PartialSol,
-                         defs: &[Definition]) -> Result<Vec<PartialSol>, String> {
+                         defs: &[Definition],
+                         strategies: &StrategySet,
+                         timing: &mut BTreeMap<String, StratStats>,
+                         mut memo: Option<&mut LemmaMemo>) -> Result<Vec<PartialSol>, String> {
     let active = partial.active();
     if active.len() == 0 {
         return Err("sol has no path forward".to_string());
     }
-    let goal_subs: Vec<(Goal, Vec<Goal>)> = active.iter().filter_map(
-            |g| match unpack_goal(g, &partial.context, defs) {
-                Ok(x) => Some(x),
-                _ => None
-            }).collect();
+    let goal_subs: Vec<(Goal, Vec<(String, Goal)>)> = active.iter().filter_map(
+            |g| unpack_goal(g, &partial.context, defs, strategies, timing, &mut memo).ok()).collect();
     let output: Vec<PartialSol> = goal_subs.iter().map(
             |(old_g, g_lst)| g_lst.iter().map(
-                move |new_g| partial.replace(&old_g, new_g)
-                )
+                move |(name, new_g)| PartialSol {
+                    strat: Some(name.clone()),
+                    ..partial.replace(old_g, new_g)
+                })
             ).flatten().collect();
     return Ok(output);
 }
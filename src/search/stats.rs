@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use crate::serial::{json_string, json_object, json_array};
+use super::trace::{SearchTrace};
+
+
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct StratStats {
+    pub expanded: u32,
+    pub produced: u32,
+    pub calls: u32,
+    pub time: Duration
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SearchOutcome {
+    Solved,
+    Exhausted,
+    Limit,
+    Timeout,
+    Failed
+}
+
+impl SearchOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchOutcome::Solved => "solved",
+            SearchOutcome::Exhausted => "exhausted",
+            SearchOutcome::Limit => "limit",
+            SearchOutcome::Timeout => "timeout",
+            SearchOutcome::Failed => "failed"
+        }
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct SearchStats {
+    pub outcome: SearchOutcome,
+    pub expansions: u32,
+    pub max_queue: usize,
+    pub elapsed: Duration,
+    pub depths: BTreeMap<u32, u32>,
+    pub strategies: BTreeMap<String, StratStats>,
+    pub frontier: Vec<String>,
+    pub trace: Option<SearchTrace>
+}
+
+pub fn millis(d: &Duration) -> String {
+    format!("{:.3}", d.as_secs_f64() * 1000.0)
+}
+
+impl SearchStats {
+    pub fn blank() -> SearchStats {
+        SearchStats {
+            outcome: SearchOutcome::Failed,
+            expansions: 0,
+            max_queue: 0,
+            elapsed: Duration::ZERO,
+            depths: BTreeMap::new(),
+            strategies: BTreeMap::new(),
+            frontier: vec![],
+            trace: None
+        }
+    }
+
+    pub fn record_expansion(&mut self, depth: u32, label: Option<String>) {
+        self.expansions += 1;
+        *self.depths.entry(depth).or_insert(0) += 1;
+        if let Some(name) = label {
+            self.strategies.entry(name).or_default().expanded += 1;
+        }
+    }
+
+    pub fn merge_strategies(&mut self, other: &BTreeMap<String, StratStats>) {
+        for (name, st) in other {
+            let entry = self.strategies.entry(name.clone()).or_default();
+            entry.produced += st.produced;
+            entry.calls += st.calls;
+            entry.time += st.time;
+        }
+    }
+
+    pub fn to_report(&self) -> String {
+        let mut lines: Vec<String> = vec![
+            format!("outcome: {}", self.outcome.name()),
+            format!("expansions: {}", self.expansions),
+            format!("max queue size: {}", self.max_queue),
+            format!("time: {} ms", millis(&self.elapsed)),
+            "depth histogram:".to_string()
+        ];
+        for (depth, count) in &self.depths {
+            lines.push(format!("  {:>4} {:>8}", depth, count));
+        }
+        lines.push(format!("strategies:\n  {:<20} {:>8} {:>8} {:>8} {:>10}",
+                           "name", "expanded", "produced", "calls", "time (ms)"));
+        for (name, st) in &self.strategies {
+            lines.push(format!("  {:<20} {:>8} {:>8} {:>8} {:>10}",
+                               name, st.expanded, st.produced, st.calls, millis(&st.time)));
+        }
+        if !self.frontier.is_empty() {
+            lines.push(format!("frontier (best {}):", self.frontier.len()));
+            for (idx, state) in self.frontier.iter().enumerate() {
+                lines.push(format!("  [{}]", idx));
+                for l in state.lines() {
+                    lines.push(format!("    {}", l));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn to_json(&self) -> String {
+        let depths: Vec<String> = self.depths.iter().map(
+            |(d, c)| json_object(&[("depth", d.to_string()), ("count", c.to_string())])
+            ).collect();
+        let strats: Vec<String> = self.strategies.iter().map(
+            |(name, st)| json_object(&[
+                ("name", json_string(name)),
+                ("expanded", st.expanded.to_string()),
+                ("produced", st.produced.to_string()),
+                ("calls", st.calls.to_string()),
+                ("time_ms", millis(&st.time))
+            ])).collect();
+        let frontier: Vec<String> = self.frontier.iter().map(|x| json_string(x)).collect();
+        json_object(&[
+            ("outcome", json_string(self.outcome.name())),
+            ("expansions", self.expansions.to_string()),
+            ("max_queue", self.max_queue.to_string()),
+            ("time_ms", millis(&self.elapsed)),
+            ("depths", json_array(&depths)),
+            ("strategies", json_array(&strats)),
+            ("frontier", json_array(&frontier))
+        ])
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_report() {
+        let mut stats = SearchStats::blank();
+        stats.outcome = SearchOutcome::Solved;
+        stats.record_expansion(0, None);
+        stats.record_expansion(1, Some("in_context".to_string()));
+        stats.record_expansion(1, Some("in_context".to_string()));
+        stats.frontier.push("A : \\ast\n?? : A".to_string());
+
+        assert_eq!(stats.expansions, 3);
+        assert_eq!(stats.depths[&1], 2);
+        assert_eq!(stats.strategies["in_context"].expanded, 2);
+        let report = stats.to_report();
+        assert!(report.starts_with("outcome: solved\nexpansions: 3\n"));
+        assert!(report.contains("    ?? : A"));
+        let json = stats.to_json();
+        assert!(json.starts_with("{\"outcome\": \"solved\", \"expansions\": 3, \"max_queue\": 0, "));
+        assert!(json.contains("\"depths\": [{\"depth\": 0, \"count\": 1}, {\"depth\": 1, \"count\": 2}]"));
+        assert!(json.contains("\"frontier\": [\"A : \\\\ast\\n?? : A\"]"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct TraceNode {
+    pub summary: String,
+    pub weight: i32,
+    pub parent: Option<usize>
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct SearchTrace {
+    pub nodes: Vec<TraceNode>,
+    pub edges: Vec<(usize, usize, String)>,
+    pub solution: Vec<usize>,
+    index: HashMap<u64, usize>
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl SearchTrace {
+    pub fn blank() -> SearchTrace {
+        SearchTrace {
+            nodes: vec![],
+            edges: vec![],
+            solution: vec![],
+            index: HashMap::new()
+        }
+    }
+
+    pub fn node(&mut self, key: u64, summary: String, weight: i32) -> usize {
+        if let Some(idx) = self.index.get(&key) {
+            return *idx;
+        }
+        self.nodes.push(TraceNode { summary, weight, parent: None });
+        self.index.insert(key, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn edge(&mut self, parent: usize, child: usize, label: String) {
+        if parent == child {
+            return;
+        }
+        if self.nodes[child].parent.is_none() && child != 0 {
+            self.nodes[child].parent = Some(parent);
+        }
+        self.edges.push((parent, child, label));
+    }
+
+    pub fn mark_solution(&mut self, last: usize) {
+        let mut path: Vec<usize> = vec![last];
+        let mut current = last;
+        while let Some(p) = self.nodes[current].parent {
+            if path.contains(&p) {
+                break;
+            }
+            path.push(p);
+            current = p;
+        }
+        path.reverse();
+        self.solution = path;
+    }
+
+    fn on_path(&self, parent: usize, child: usize) -> bool {
+        self.solution.windows(2).any(|w| w[0] == parent && w[1] == child)
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut lines: Vec<String> = vec![
+            "digraph search {".to_string(),
+            "\tnode [shape=box, fontname=\"monospace\"];".to_string()
+        ];
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let style = if self.solution.contains(&idx) {
+                ", color=green, penwidth=2"
+            } else {
+                ""
+            };
+            lines.push(format!("\tn{} [label=\"{}\\nw={}\"{}];",
+                               idx, dot_escape(&node.summary), node.weight, style));
+        }
+        for (parent, child, label) in &self.edges {
+            let style = if self.on_path(*parent, *child) {
+                ", color=green, penwidth=2"
+            } else {
+                ""
+            };
+            lines.push(format!("\tn{} -> n{} [label=\"{}\"{}];",
+                               parent, child, dot_escape(label), style));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_trace_to_dot() {
+        let mut trace = SearchTrace::blank();
+        let a = trace.node(1, "i=1 u=0 f=0".to_string(), -10);
+        let b = trace.node(2, "i=1 u=1 f=0".to_string(), -20);
+        let c = trace.node(3, "i=0 u=1 f=1".to_string(), 0);
+        assert_eq!(trace.node(2, "other".to_string(), 0), b);
+        trace.edge(a, b, "unpack_type_abs".to_string());
+        trace.edge(a, c, "known_arrow".to_string());
+        trace.edge(b, c, "in_context".to_string());
+        trace.mark_solution(c);
+        assert_eq!(trace.solution, vec![0, 2]);
+        assert_eq!(trace.to_dot().split("\n").collect::<Vec<&str>>(), [
+            "digraph search {",
+            "\tnode [shape=box, fontname=\"monospace\"];",
+            "\tn0 [label=\"i=1 u=0 f=0\\nw=-10\", color=green, penwidth=2];",
+            "\tn1 [label=\"i=1 u=1 f=0\\nw=-20\"];",
+            "\tn2 [label=\"i=0 u=1 f=1\\nw=0\", color=green, penwidth=2];",
+            "\tn0 -> n1 [label=\"unpack_type_abs\"];",
+            "\tn0 -> n2 [label=\"known_arrow\", color=green, penwidth=2];",
+            "\tn1 -> n2 [label=\"in_context\"];",
+            "}"
+        ]);
+    }
+}
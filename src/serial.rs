@@ -1,4 +1,62 @@
 
 mod flagderiv;
+mod bussproofs;
+mod nesting;
+mod fitch;
+mod logicproof;
+mod json;
+mod result;
 
 pub use self::flagderiv::{flagderiv_output};
+pub use self::bussproofs::{bussproofs_output};
+pub use self::fitch::{fitch_output};
+pub use self::logicproof::{logicproof_output};
+pub use self::json::{json_string, json_object, json_array};
+pub use self::result::{result_json, ErrorKind};
+
+
+#[cfg(test)]
+mod fixture {
+    use crate::model::proof::{Proof};
+    use crate::model::def::{Definition};
+    use crate::model::judgement::{Judgement};
+    use crate::model::pts::{PtsSpec};
+    use crate::parser::{parse_judgement, parse_definition};
+    use crate::unpack_term::{unpack_term_in};
+    use crate::type_check::{check_proof_in};
+
+    pub fn checked_proof(jdg_str: &str, defs: &[Definition]) -> Proof {
+        checked_proof_in(&PtsSpec::coc(), jdg_str, defs)
+    }
+
+    pub fn checked_proof_in(spec: &PtsSpec, jdg_str: &str, defs: &[Definition]) -> Proof {
+        let jdg = parse_judgement(jdg_str).unwrap();
+        let lines = unpack_term_in(spec, &jdg.statement.subject, &jdg.context, defs).unwrap();
+        let refs = check_proof_in(spec, defs, &lines).unwrap();
+        Proof { lines, refs }
+    }
+
+    // a derivation written out line by line, for orders the unpacker does not produce
+    pub fn checked_lines(lines: &[&str], defs: &[Definition]) -> Proof {
+        let lines: Vec<Judgement> = lines.iter().map(|x| parse_judgement(x).unwrap()).collect();
+        let refs = check_proof_in(&PtsSpec::coc(), defs, &lines).unwrap();
+        Proof { lines, refs }
+    }
+
+    // the inner subproof closes two levels at once and the body uses an instantiated definition
+    pub fn nested_inst() -> Proof {
+        let defs = vec![parse_definition("A : \\ast \\vartriangleright id \\langle A \\rangle := \\lambda x : A . x : A \\to A").unwrap()];
+        checked_lines(&[
+            "\\vdash \\ast : \\square",
+            "A : \\ast \\vdash A : \\ast",
+            "A : \\ast, x : A \\vdash A : \\ast",
+            "A : \\ast, x : A, y : A \\vdash y : A",
+            "A : \\ast, x : A, y : A \\vdash A : \\ast",
+            "A : \\ast, x : A \\vdash A \\to A : \\ast",
+            "A : \\ast, x : A, y : A \\vdash id \\langle A \\rangle : A \\to A",
+            "A : \\ast, x : A, y : A \\vdash id \\langle A \\rangle y : A",
+            "A : \\ast \\vdash A \\to A \\to A : \\ast",
+            "A : \\ast, x : A \\vdash \\lambda y : A . id \\langle A \\rangle y : A \\to A",
+            "A : \\ast \\vdash \\lambda x : A . \\lambda y : A . id \\langle A \\rangle y : A \\to A \\to A"], &defs)
+    }
+}
@@ -0,0 +1,185 @@
+use crate::model::proof::{Proof};
+use crate::model::def::{Definition};
+use crate::model::pts::{PtsSpec};
+use crate::model::rules::{all_rules_in};
+
+
+// shared sub-derivations up to this many inferences are repeated instead of referenced
+const SHARE_SIZE: u64 = 4;
+
+const INFERENCES: [&str; 6] = ["\\AxiomC", "\\UnaryInfC", "\\BinaryInfC", "\\TrinaryInfC",
+                               "\\QuaternaryInfC", "\\QuinaryInfC"];
+
+fn premises(proof: &Proof, spec: &PtsSpec, defs: &[Definition]) -> Vec<Vec<usize>> {
+    let inst = all_rules_in(spec, defs).into_iter().find(|r| r.name() == "inst");
+    proof.lines.iter().zip(&proof.refs).enumerate().map(|(idx, (jdg, rf))| {
+        let many = match &inst {
+            Some(rule) if rf.rule == "inst" => rule.validate_many(&proof.lines[..idx], jdg),
+            _ => None
+        };
+        match many {
+            Some(lines) => lines.iter().map(|x| *x as usize).collect(),
+            None => [rf.line1, rf.line2].iter().filter_map(|x| x.map(|l| l as usize)).collect()
+        }
+    }).collect()
+}
+
+fn uses(prems: &[Vec<usize>], root: usize) -> Vec<usize> {
+    let mut count: Vec<usize> = vec![0; prems.len()];
+    let mut seen: Vec<bool> = vec![false; prems.len()];
+    let mut stack: Vec<usize> = vec![root];
+    while let Some(idx) = stack.pop() {
+        if seen[idx] {
+            continue;
+        }
+        seen[idx] = true;
+        for p in &prems[idx] {
+            count[*p] += 1;
+            stack.push(*p);
+        }
+    }
+    count
+}
+
+fn sizes(prems: &[Vec<usize>]) -> Vec<u64> {
+    let mut output: Vec<u64> = vec![];
+    for lines in prems {
+        let size = lines.iter().filter(|p| **p < output.len()).fold(
+            1u64, |acc, p| acc.saturating_add(output[*p]));
+        output.push(size);
+    }
+    output
+}
+
+fn tree(idx: usize, proof: &Proof, prems: &[Vec<usize>], shared: &[bool],
+        top: bool, output: &mut Vec<String>) -> Result<(), String> {
+    let jdg = &proof.lines[idx];
+    if shared[idx] && !top {
+        output.push(format!("\\AxiomC{{$\\vdots$ $({})$}}", idx));
+        output.push("\\noLine".to_string());
+        output.push(format!("\\UnaryInfC{{${}$}}", jdg.to_latex()));
+        return Ok(());
+    }
+    let lines = &prems[idx];
+    if let Some(p) = lines.iter().find(|p| **p >= idx) {
+        return Err(format!("line {}: premise {} is not an earlier line", idx, p));
+    }
+    if lines.len() >= INFERENCES.len() {
+        // too many premises for one inference, they are typeset as their own trees and referenced
+        let refs: Vec<String> = lines.iter().map(|p| format!("({})", p)).collect();
+        output.push(format!("\\AxiomC{{$\\vdots$ ${}$}}", refs.join(" \\quad ")));
+    } else {
+        for p in lines {
+            tree(*p, proof, prems, shared, false, output)?;
+        }
+    }
+    if lines.is_empty() {
+        output.push("\\AxiomC{}".to_string());
+    }
+    if top && shared[idx] {
+        output.push(format!("\\LeftLabel{{$({})$}}", idx));
+    }
+    output.push(format!("\\RightLabel{{{}}}", proof.refs[idx].rule));
+    let inference = INFERENCES.get(lines.len().max(1)).unwrap_or(&INFERENCES[1]);
+    output.push(format!("{}{{${}$}}", inference, jdg.to_latex()));
+    Ok(())
+}
+
+pub fn bussproofs_output(proof: &Proof, spec: &PtsSpec, defs: &[Definition]) -> Result<String, String> {
+    if proof.lines.is_empty() || proof.lines.len() != proof.refs.len() {
+        return Err(format!("proof has {} lines but {} justifications",
+                           proof.lines.len(), proof.refs.len()));
+    }
+    let root = proof.lines.len() - 1;
+    let prems = premises(proof, spec, defs);
+    let count = uses(&prems, root);
+    let size = sizes(&prems);
+    let mut shared: Vec<bool> = (0..prems.len()).map(|i| count[i] > 1 && size[i] > SHARE_SIZE).collect();
+    for (idx, lines) in prems.iter().enumerate() {
+        if lines.len() >= INFERENCES.len() && (count[idx] > 0 || idx == root) {
+            for p in lines.iter().filter(|p| **p < idx) {
+                shared[*p] = true;
+            }
+        }
+    }
+    let mut trees: Vec<String> = vec![];
+    for idx in (0..root).filter(|i| shared[*i]).chain(std::iter::once(root)) {
+        let mut output: Vec<String> = vec!["\\begin{prooftree}".to_string()];
+        tree(idx, proof, &prems, &shared, true, &mut output)?;
+        output.push("\\end{prooftree}".to_string());
+        trees.push(output.join("\n"));
+    }
+    Ok(trees.join("\n\n"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_definition};
+    use crate::serial::fixture::{checked_proof, checked_proof_in};
+
+    #[test]
+    fn identity_tree() {
+        let proof = checked_proof("A:\\ast \\vdash \\lambda x : A . x : A \\to A", &[]);
+        let output = bussproofs_output(&proof, &PtsSpec::coc(), &[]).unwrap();
+        let axiom = ["\\AxiomC{}", "\\RightLabel{sort}", "\\UnaryInfC{$\\vdash \\ast : \\square$}",
+                     "\\RightLabel{var}", "\\UnaryInfC{$A : \\ast \\vdash A : \\ast$}"];
+        assert_eq!(output.split("\n").collect::<Vec<&str>>(), [
+            vec!["\\begin{prooftree}"],
+            axiom.to_vec(),
+            vec!["\\RightLabel{var}", "\\UnaryInfC{$A : \\ast, a : A \\vdash a : A$}"],
+            axiom.to_vec(),
+            axiom.to_vec(),
+            axiom.to_vec(),
+            vec!["\\RightLabel{weak}",
+                 "\\BinaryInfC{$A : \\ast, a : A \\vdash A : \\ast$}",
+                 "\\RightLabel{form}",
+                 "\\BinaryInfC{$A : \\ast \\vdash A \\to A : \\ast$}",
+                 "\\RightLabel{abst}",
+                 "\\BinaryInfC{$A : \\ast \\vdash \\lambda x : A . x : A \\to A$}",
+                 "\\end{prooftree}"]
+        ].concat());
+    }
+
+    #[test]
+    fn shared_references() {
+        let proof = checked_proof("A : \\ast, B : \\ast \\vdash \\lambda c : A \\wedge B . c A (\\lambda d : A . \\lambda b : B . d) : (A \\wedge B) \\to A", &[]);
+        let output = bussproofs_output(&proof, &PtsSpec::coc(), &[]).unwrap();
+        let trees: Vec<&str> = output.split("\n\n").collect();
+        assert!(trees.len() > 1);
+        for (idx, tree) in trees.iter().enumerate() {
+            assert!(tree.starts_with("\\begin{prooftree}\n") && tree.ends_with("\n\\end{prooftree}"));
+            for line in tree.lines().filter(|x| x.starts_with("\\AxiomC{$\\vdots$ ")) {
+                let label = format!("\\LeftLabel{{{}}}", &line["\\AxiomC{$\\vdots$ ".len()..line.len() - 1]);
+                assert!(trees[..idx].iter().any(|t| t.contains(&label)), "{} has no earlier tree", line);
+            }
+        }
+    }
+
+    #[test]
+    fn inst_premises() {
+        let defs = vec![parse_definition(
+            "A : \\ast, B : \\ast, C : \\ast \\vartriangleright k \\langle A, B, C \\rangle := \\lambda x : A . \\lambda y : B . \\lambda z : C . x : A \\to B \\to C \\to A").unwrap()];
+        let proof = checked_proof("D : \\ast \\vdash k \\langle D, D, D \\rangle : D \\to D \\to D \\to D", &defs);
+        assert_eq!(proof.refs.last().unwrap().rule, "inst");
+        let output = bussproofs_output(&proof, &PtsSpec::coc(), &defs).unwrap();
+        assert!(output.ends_with("\\RightLabel{inst}\n\\TrinaryInfC{$D : \\ast \\vdash k \\langle D, D, D \\rangle : D \\to D \\to D \\to D$}\n\\end{prooftree}"));
+        assert!(bussproofs_output(&Proof { lines: vec![], refs: vec![] }, &PtsSpec::coc(), &[]).is_err());
+    }
+
+    #[test]
+    fn wide_inst_references() {
+        let defs = vec![parse_definition(
+            "A : \\ast, B : \\ast, C : \\ast, D : \\ast, E : \\ast, F : \\ast \\vartriangleright k \\langle A, B, C, D, E, F \\rangle := \\lambda x : A . x : A \\to A").unwrap()];
+        let proof = checked_proof_in(&PtsSpec::lambda2(), "G : \\ast \\vdash k \\langle G, G, G, G, G, G \\rangle : G \\to G", &defs);
+        let output = bussproofs_output(&proof, &PtsSpec::lambda2(), &defs).unwrap();
+        let trees: Vec<&str> = output.split("\n\n").collect();
+        let last = trees.last().unwrap();
+        assert!(last.ends_with("\\RightLabel{inst}\n\\UnaryInfC{$G : \\ast \\vdash k \\langle G, G, G, G, G, G \\rangle : G \\to G$}\n\\end{prooftree}"));
+        let refs_line = last.lines().find(|x| x.starts_with("\\AxiomC{$\\vdots$ $(")).unwrap();
+        for label in refs_line["\\AxiomC{$\\vdots$ $".len()..refs_line.len() - 2].split(" \\quad ") {
+            assert!(trees.iter().any(|t| t.contains(&format!("\\LeftLabel{{${}$}}", label))), "{} has no tree", label);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::model::proof::{Proof};
+use super::nesting::{Entry, nested_lines, line_numbers, numbered_refs};
+
+
+pub fn fitch_output(proof: &Proof) -> Result<String, String> {
+    let entries = nested_lines(proof)?;
+    let numbers = line_numbers(&entries, proof.lines.len());
+    let mut output: Vec<String> = vec!["\\begin{nd}".to_string()];
+    let mut depth: usize = 0;
+    for (n, entry) in entries.iter().enumerate() {
+        match entry {
+            Entry::Assume(stmt) => {
+                output.push("\t\\open".to_string());
+                depth += 1;
+                output.push(format!("\t\\hypo{{{}}}{{{}}}", n + 1, stmt.to_latex()));
+            },
+            Entry::Step(_, stmt, rf, leap) => {
+                depth -= leap;
+                for _ in 0..*leap {
+                    output.push("\t\\close".to_string());
+                }
+                output.push(format!("\t\\have{{{}}}{{{}}} \\by{{{}}}{{{}}}",
+                                    n + 1, stmt.to_latex(), rf.rule, numbered_refs(rf, &numbers)));
+            }
+        }
+    }
+    output.extend(std::iter::repeat_n("\t\\close".to_string(), depth));
+    output.push("\\end{nd}".to_string());
+    Ok(output.join("\n"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::fixture::{checked_proof, nested_inst};
+
+    #[test]
+    fn golden_k() {
+        let proof = checked_proof("A : \\ast, B : \\ast \\vdash \\lambda x : A . \\lambda y : B . x : A \\to B \\to A", &[]);
+        assert_eq!(fitch_output(&proof).unwrap() + "\n", include_str!("golden/k.fitch.tex"));
+    }
+
+    #[test]
+    fn golden_nested_inst() {
+        assert_eq!(fitch_output(&nested_inst()).unwrap() + "\n", include_str!("golden/nested_inst.fitch.tex"));
+    }
+}
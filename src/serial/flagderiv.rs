@@ -2,17 +2,14 @@
 use crate::model::proof::Proof;
 use crate::model::statement::Statement;
 use crate::model::proof::{LineRef};
+use super::nesting::{Entry, nested_lines};
 
 
-fn valid_context_change(lhs: &[Statement], rhs: &[Statement]) -> bool {
-    lhs.iter().zip(rhs).all(|(x,y)| x == y)
-}
-
-fn step(idx: i32, stmt: &Statement, rf: &LineRef) -> String {
+fn step(idx: usize, stmt: &Statement, rf: &LineRef) -> String {
     format!("\t\\step*{{$({})$}}{{{}}}{{{}}}", idx, stmt.to_latex(), rf.to_latex())
 }
 
-fn conclude(leap: i32, idx: i32, stmt: &Statement, rf: &LineRef) -> String {
+fn conclude(leap: usize, idx: usize, stmt: &Statement, rf: &LineRef) -> String {
     format!("\t\\conclude*[{}]{{$({})$}}{{{}}}{{{}}}", leap, idx, stmt.to_latex(), rf.to_latex())
 }
 
@@ -23,25 +20,11 @@ fn assume(stmt: &Statement) -> String {
 pub fn flagderiv_output(proof: &Proof) -> Result<String,String> {
     let s_token = "\\begin{flagderiv}".to_string();
     let e_token = "\\end{flagderiv}".to_string();
-    let mut current_ctx: &Vec<Statement> = &vec![];
-    let mut output: Vec<String> = vec![];
-    for (idx, (jdg, rf)) in proof.lines.iter().zip(&proof.refs).enumerate() {
-        if !valid_context_change(&current_ctx, &jdg.context) {
-            return Err(format!("invalid context change: [{}] to [{}]", Statement::ctx_str(&current_ctx), Statement::ctx_str(&jdg.context)));
-        }
-        if current_ctx.len() < jdg.context.len() {
-            for stmt in jdg.context[current_ctx.len()..].iter() {
-                output.push(assume(&stmt));
-            }
-        }
-        if current_ctx.len() > jdg.context.len() {
-            output.push(conclude(current_ctx.len() as i32 - jdg.context.len() as i32,
-            idx as i32, &jdg.statement, &rf));
-        } else {
-            output.push(step(idx as i32, &jdg.statement, &rf));
-        }
-        current_ctx = &jdg.context;
-    }
+    let output: Vec<String> = nested_lines(proof)?.iter().map(|entry| match entry {
+        Entry::Assume(stmt) => assume(stmt),
+        Entry::Step(idx, stmt, rf, 0) => step(*idx, stmt, rf),
+        Entry::Step(idx, stmt, rf, leap) => conclude(*leap, *idx, stmt, rf)
+    }).collect();
     Ok(std::iter::once(s_token).chain(output).chain(std::iter::once(e_token)).collect::<Vec<String>>().join("\n"))
 }
 
@@ -49,16 +32,11 @@ pub fn flagderiv_output(proof: &Proof) -> Result<String,String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::{parse_judgement};
-    use crate::unpack_term::{unpack_term};
-    use crate::type_check::{check_proof};
+    use crate::serial::fixture::{checked_proof, nested_inst};
 
     #[test]
     fn simple_identity_proof() {
-        let jdg = parse_judgement("A:\\ast \\vdash \\lambda x : A . x : A \\to A").unwrap();
-        let lines = unpack_term(&jdg.statement.subject, &jdg.context, &[]).unwrap();
-        let refs = check_proof(&[], &lines).unwrap();
-        let proof = Proof { lines: lines, refs: refs };
+        let proof = checked_proof("A:\\ast \\vdash \\lambda x : A . x : A \\to A", &[]);
         let output = flagderiv_output(&proof).unwrap();
         let str_lines: Vec<String> = output.split("\n").map(|x| x.trim().to_string()).collect();
         println!("{}", output);
@@ -75,5 +53,15 @@ mod tests {
            "\\end{flagderiv}"
         ]);
     }
-}
 
+    #[test]
+    fn golden_k() {
+        let proof = checked_proof("A : \\ast, B : \\ast \\vdash \\lambda x : A . \\lambda y : B . x : A \\to B \\to A", &[]);
+        assert_eq!(flagderiv_output(&proof).unwrap() + "\n", include_str!("golden/k.flagderiv.tex"));
+    }
+
+    #[test]
+    fn golden_nested_inst() {
+        assert_eq!(flagderiv_output(&nested_inst()).unwrap() + "\n", include_str!("golden/nested_inst.flagderiv.tex"));
+    }
+}
@@ -0,0 +1,44 @@
+
+
+pub fn json_string(s: &str) -> String {
+    let mut output = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '\r' => output.push_str("\\r"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c)
+        }
+    }
+    output.push('"');
+    output
+}
+
+pub fn json_object(fields: &[(&str, String)]) -> String {
+    let inner: Vec<String> = fields.iter().map(
+        |(k, v)| format!("{}: {}", json_string(k), v)).collect();
+    format!("{{{}}}", inner.join(", "))
+}
+
+pub fn json_array(items: &[String]) -> String {
+    format!("[{}]", items.join(", "))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_json() {
+        assert_eq!(json_string("\\lambda x : A . x"), "\"\\\\lambda x : A . x\"");
+        assert_eq!(json_string("a\"b\n"), "\"a\\\"b\\n\"");
+        assert_eq!(json_object(&[("a", "1".to_string()), ("b", json_string("x"))]),
+                   "{\"a\": 1, \"b\": \"x\"}");
+        assert_eq!(json_array(&["1".to_string(), "2".to_string()]), "[1, 2]");
+        assert_eq!(json_array(&[]), "[]");
+    }
+}
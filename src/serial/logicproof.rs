@@ -0,0 +1,58 @@
+use crate::model::proof::{Proof};
+use super::nesting::{Entry, nested_lines, line_numbers, numbered_refs, max_depth};
+
+
+// logicproof rows are separated by \\, which must not precede an \end
+pub fn logicproof_output(proof: &Proof) -> Result<String, String> {
+    let entries = nested_lines(proof)?;
+    let numbers = line_numbers(&entries, proof.lines.len());
+    let mut output: Vec<String> = vec![format!("\\begin{{logicproof}}{{{}}}", max_depth(proof))];
+    let mut depth: usize = 0;
+    for (n, entry) in entries.iter().enumerate() {
+        let (indent, row) = match entry {
+            Entry::Assume(stmt) => {
+                output.push(format!("{}\\begin{{subproof}}", "\t".repeat(depth + 1)));
+                depth += 1;
+                (depth + 1, format!("{} & assumption", stmt.to_latex()))
+            },
+            Entry::Step(_, stmt, rf, leap) => {
+                for _ in 0..*leap {
+                    depth -= 1;
+                    output.push(format!("{}\\end{{subproof}}", "\t".repeat(depth + 1)));
+                }
+                (depth + 1, format!("{} & {} {}", stmt.to_latex(), rf.rule, numbered_refs(rf, &numbers)))
+            }
+        };
+        let ends = match entries.get(n + 1) {
+            Some(Entry::Step(_, _, _, leap)) => *leap > 0,
+            Some(Entry::Assume(_)) => false,
+            None => true
+        };
+        let row = row.trim_end().to_string();
+        output.push(format!("{}{}{}", "\t".repeat(indent), row, if ends { "" } else { "\\\\" }));
+    }
+    while depth > 0 {
+        depth -= 1;
+        output.push(format!("{}\\end{{subproof}}", "\t".repeat(depth + 1)));
+    }
+    output.push("\\end{logicproof}".to_string());
+    Ok(output.join("\n"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::fixture::{checked_proof, nested_inst};
+
+    #[test]
+    fn golden_k() {
+        let proof = checked_proof("A : \\ast, B : \\ast \\vdash \\lambda x : A . \\lambda y : B . x : A \\to B \\to A", &[]);
+        assert_eq!(logicproof_output(&proof).unwrap() + "\n", include_str!("golden/k.logicproof.tex"));
+    }
+
+    #[test]
+    fn golden_nested_inst() {
+        assert_eq!(logicproof_output(&nested_inst()).unwrap() + "\n", include_str!("golden/nested_inst.logicproof.tex"));
+    }
+}
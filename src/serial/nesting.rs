@@ -0,0 +1,75 @@
+use crate::model::proof::{Proof, LineRef};
+use crate::model::statement::{Statement};
+
+
+pub enum Entry<'a> {
+    Assume(&'a Statement),
+    Step(usize, &'a Statement, &'a LineRef, usize)
+}
+
+fn valid_context_change(lhs: &[Statement], rhs: &[Statement]) -> bool {
+    lhs.iter().zip(rhs).all(|(x,y)| x == y)
+}
+
+// each statement added to the context opens a subproof, a step with a shorter context closes (leaps) them
+pub fn nested_lines(proof: &Proof) -> Result<Vec<Entry<'_>>, String> {
+    let mut current_ctx: &[Statement] = &[];
+    let mut output: Vec<Entry> = vec![];
+    for (idx, (jdg, rf)) in proof.lines.iter().zip(&proof.refs).enumerate() {
+        if !valid_context_change(current_ctx, &jdg.context) {
+            return Err(format!("invalid context change: [{}] to [{}]", Statement::ctx_str(current_ctx), Statement::ctx_str(&jdg.context)));
+        }
+        for stmt in jdg.context.iter().skip(current_ctx.len()) {
+            output.push(Entry::Assume(stmt));
+        }
+        output.push(Entry::Step(idx, &jdg.statement, rf, current_ctx.len().saturating_sub(jdg.context.len())));
+        current_ctx = &jdg.context;
+    }
+    Ok(output)
+}
+
+pub fn max_depth(proof: &Proof) -> usize {
+    proof.lines.iter().map(|j| j.context.len()).max().unwrap_or(0)
+}
+
+// lines numbered from 1 in order of appearance, assumptions included
+pub fn line_numbers(entries: &[Entry], len: usize) -> Vec<usize> {
+    let mut numbers: Vec<usize> = vec![0; len];
+    for (n, entry) in entries.iter().enumerate() {
+        if let Entry::Step(idx, ..) = entry {
+            numbers[*idx] = n + 1;
+        }
+    }
+    numbers
+}
+
+pub fn numbered_refs(rf: &LineRef, numbers: &[usize]) -> String {
+    [rf.line1, rf.line2].iter().filter_map(|x| x.and_then(|l| numbers.get(l as usize)))
+        .map(|n| n.to_string())
+        .collect::<Vec<String>>().join(",")
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::fixture::{checked_proof};
+
+    #[test]
+    fn identity_nesting() {
+        let mut proof = checked_proof("A:\\ast \\vdash \\lambda x : A . x : A \\to A", &[]);
+        let entries = nested_lines(&proof).unwrap();
+        let shape: Vec<String> = entries.iter().map(|e| match e {
+            Entry::Assume(stmt) => stmt.to_latex(),
+            Entry::Step(idx, _, _, leap) => format!("{}/{}", idx, leap)
+        }).collect();
+        assert_eq!(shape, ["0/0", "A : \\ast", "1/0", "a : A", "2/0", "3/0", "4/1", "5/0"]);
+        assert_eq!(line_numbers(&entries, proof.lines.len()), [1, 3, 5, 6, 7, 8]);
+        assert_eq!(numbered_refs(&proof.refs[4], &line_numbers(&entries, 6)), "3,6");
+        assert_eq!(max_depth(&proof), 2);
+
+        proof.lines[2].context.reverse();
+        assert!(nested_lines(&proof).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+use crate::model::statement::{Statement};
+use crate::model::judgement::{Judgement};
+use crate::model::proof::{Proof, LineRef};
+use crate::search::stats::{SearchStats, SearchOutcome, millis};
+use super::json::{json_string, json_object, json_array};
+
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+fn statement_json(stmt: &Statement) -> Vec<(&'static str, String)> {
+    vec![("subject", json_string(&stmt.subject.to_latex())),
+         ("type", json_string(&stmt.s_type.to_latex()))]
+}
+
+pub fn judgement_json(jdg: &Judgement) -> Vec<(&'static str, String)> {
+    let context: Vec<String> = jdg.context.iter().map(|x| json_object(&statement_json(x))).collect();
+    [vec![("context", json_array(&context))], statement_json(&jdg.statement)].concat()
+}
+
+fn line_json(idx: usize, jdg: &Judgement, rf: &LineRef) -> String {
+    let premises: Vec<String> = [rf.line1, rf.line2].iter().filter_map(|x| x.map(|l| l.to_string())).collect();
+    json_object(&[vec![("index", idx.to_string())],
+                  judgement_json(jdg),
+                  vec![("rule", json_string(&rf.rule)), ("premises", json_array(&premises))]].concat())
+}
+
+pub fn proof_json(proof: &Proof) -> String {
+    let lines: Vec<String> = proof.lines.iter().zip(&proof.refs).enumerate().map(
+        |(idx, (jdg, rf))| line_json(idx, jdg, rf)).collect();
+    json_array(&lines)
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ErrorKind {
+    Usage,
+    Theory,
+    Goal,
+    Type,
+    Search(SearchOutcome)
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Usage => "usage",
+            ErrorKind::Theory => "theory",
+            ErrorKind::Goal => "goal",
+            ErrorKind::Type => "type",
+            ErrorKind::Search(SearchOutcome::Exhausted) => "exhausted",
+            ErrorKind::Search(SearchOutcome::Limit) => "limit",
+            ErrorKind::Search(SearchOutcome::Timeout) => "timeout",
+            ErrorKind::Search(_) => "search"
+        }
+    }
+}
+
+pub fn result_json(mode: &str, res: &Result<Proof, (ErrorKind, String)>,
+                   stats: Option<&SearchStats>, elapsed: &Duration) -> String {
+    let mut fields: Vec<(&str, String)> = vec![
+        ("version", SCHEMA_VERSION.to_string()),
+        ("mode", json_string(mode))
+    ];
+    match res {
+        Ok(proof) => {
+            let last = proof.lines.last().unwrap();
+            fields.push(("status", json_string("ok")));
+            fields.push(("term", json_string(&last.statement.subject.to_latex())));
+            fields.push(("judgement", json_object(&judgement_json(last))));
+            fields.push(("proof", proof_json(proof)));
+        },
+        Err((kind, msg)) => {
+            fields.push(("status", json_string("error")));
+            fields.push(("error", json_object(&[("kind", json_string(kind.name())),
+                                                ("message", json_string(msg))])));
+        }
+    }
+    fields.push(("time_ms", millis(elapsed)));
+    fields.push(("stats", stats.map_or("null".to_string(), |st| st.to_json())));
+    json_object(&fields)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{Environment};
+
+    #[test]
+    fn result_schema() {
+        let env = Environment::blank();
+        let proof = env.verify("A : \\ast \\vdash \\lambda x : A . x : A \\to A", false).map_err(|msg| (ErrorKind::Type, msg));
+        let text = result_json("check", &proof, None, &Duration::from_millis(2));
+        assert!(text.starts_with("{\"version\": 1, \"mode\": \"check\", \"status\": \"ok\", \
+                                  \"term\": \"\\\\lambda x : A . x\", \
+                                  \"judgement\": {\"context\": [{\"subject\": \"A\", \"type\": \"\\\\ast\"}], \
+                                  \"subject\": \"\\\\lambda x : A . x\", \"type\": \"A \\\\to A\"}, \
+                                  \"proof\": [{\"index\": 0, \"context\": [], \"subject\": \"\\\\ast\", \
+                                  \"type\": \"\\\\square\", \"rule\": \"sort\", \"premises\": []}, "));
+        assert!(text.contains("\"rule\": \"abst\", \"premises\": [2, 4]}]"));
+        assert!(text.ends_with(", \"time_ms\": 2.000, \"stats\": null}"));
+
+        let err: Result<Proof, (ErrorKind, String)> = Err((ErrorKind::Search(SearchOutcome::Timeout), "timed out after 5 ms".to_string()));
+        let text = result_json("search", &err, Some(&SearchStats::blank()), &Duration::ZERO);
+        assert!(text.starts_with("{\"version\": 1, \"mode\": \"search\", \"status\": \"error\", \
+                                  \"error\": {\"kind\": \"timeout\", \"message\": \"timed out after 5 ms\"}, \
+                                  \"time_ms\": 0.000, \"stats\": {\"outcome\": "));
+        assert_eq!(ErrorKind::Search(SearchOutcome::Failed).name(), "search");
+        assert_eq!(ErrorKind::Goal.name(), "goal");
+    }
+}
@@ -0,0 +1,36 @@
+const BUNDLED: [(&str, &str); 4] = [
+    ("std/logic", include_str!("stdlib/logic.tex")),
+    ("std/equality", include_str!("stdlib/equality.tex")),
+    ("std/church", include_str!("stdlib/church.tex")),
+    ("std/book", include_str!("stdlib/book.tex"))
+];
+
+#[cfg(test)]
+pub fn bundled_names() -> Vec<String> {
+    BUNDLED.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+pub fn bundled_theory(name: &str) -> Option<&'static str> {
+    BUNDLED.iter().find(|(n, _)| *n == name).map(|(_, text)| *text)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{Environment};
+    use crate::type_check::{check_proof};
+
+    #[test]
+    fn recheck_bundled() {
+        for name in bundled_names() {
+            let mut env = Environment::blank();
+            env.import(&name, None).unwrap();
+            assert!(!env.defs.is_empty(), "{}", name);
+            for (idx, proof) in env.derivations.iter().enumerate() {
+                let refs = check_proof(&env.defs[..idx], &proof.lines).unwrap();
+                assert_eq!(refs, proof.refs);
+            }
+        }
+    }
+}
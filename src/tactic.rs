@@ -0,0 +1,162 @@
+use crate::model::statement::{Statement};
+use crate::model::expression::{CCExpression};
+use crate::model::partial::{Goal, PartialSol, WithConc};
+use crate::model::proof::{Proof};
+use crate::environment::{Environment};
+use crate::find_term::{search_goal, proof_from_search};
+use crate::search::proof::{goal_alternatives, recursive_finalize};
+
+
+pub struct Tactic {
+    pub context: Vec<Statement>,
+    states: Vec<PartialSol>
+}
+
+fn alt_to_latex(g: &Goal) -> String {
+    match g {
+        Goal::Unpacked(inst, ex, subs, _) => {
+            let mut lines: Vec<String> = vec![format!("{} : {}", inst.to_latex(), ex.to_latex())];
+            for (idx, sub) in subs.iter().enumerate() {
+                let text = match sub {
+                    Goal::Final(jdgs) => jdgs.last().unwrap().statement.to_latex(),
+                    _ => sub.to_latex()
+                };
+                lines.push(format!("    sub_{{{}}} := {}", idx, text));
+            }
+            lines.join("\n")
+        },
+        Goal::Final(jdgs) => jdgs.last().unwrap().statement.to_latex(),
+        Goal::Initial(_, _) => g.to_latex()
+    }
+}
+
+impl Tactic {
+    pub fn new(s_type: &CCExpression, context: &[Statement]) -> Tactic {
+        Tactic {
+            context: context.to_vec(),
+            states: vec![PartialSol {
+                context: context.to_vec(),
+                goals: vec![Goal::Initial(s_type.clone(), vec![])],
+                strat: None
+            }]
+        }
+    }
+
+    pub fn current(&self) -> &PartialSol {
+        self.states.last().unwrap()
+    }
+
+    fn goal(&self, idx: usize) -> Result<WithConc, String> {
+        let open = self.current().active();
+        if idx == 0 || idx > open.len() {
+            return Err(format!("no goal {} ({} open)", idx, open.len()));
+        }
+        Ok(open[idx - 1].clone())
+    }
+
+    fn step(&mut self, old_g: &Goal, new_g: &Goal, name: &str) {
+        let next = PartialSol {
+            strat: Some(name.to_string()),
+            ..self.current().replace(old_g, new_g)
+        };
+        self.states.push(next);
+    }
+
+    pub fn show(&self) -> String {
+        let open = self.current().active();
+        if open.is_empty() {
+            return "no open goals (use :qed)".to_string();
+        }
+        let mut lines: Vec<String> = vec![self.current().to_latex()];
+        for (idx, g) in open.iter().enumerate() {
+            lines.push(format!("goal {}: {}", idx + 1, g.goal.to_latex()));
+        }
+        lines.join("\n")
+    }
+
+    pub fn alternatives(&self, idx: usize, env: &Environment) -> Result<String, String> {
+        let g = self.goal(idx)?;
+        let alts = goal_alternatives(&g, &self.context, &env.defs, &env.search.strategies, None)?;
+        if alts.is_empty() {
+            return Err(format!("no strategy applies to {}", g.goal.to_latex()));
+        }
+        let mut lines: Vec<String> = vec![];
+        let mut count: usize = 0;
+        for (pos, (name, alt)) in alts.iter().enumerate() {
+            count = if pos > 0 && &alts[pos - 1].0 == name { count + 1 } else { 1 };
+            lines.push(format!("{}/{}: {}", name, count, alt_to_latex(alt)));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    pub fn apply(&mut self, name: &str, alt: usize, idx: usize, env: &Environment) -> Result<(), String> {
+        let g = self.goal(idx)?;
+        let alts = goal_alternatives(&g, &self.context, &env.defs, &env.search.strategies, Some(name))?;
+        if alt == 0 || alt > alts.len() {
+            return Err(format!("{} has no alternative {} for {} ({} found)",
+                               name, alt, g.goal.to_latex(), alts.len()));
+        }
+        self.step(&g.goal, &alts[alt - 1].1, name);
+        Ok(())
+    }
+
+    pub fn auto(&mut self, idx: usize, env: &Environment) -> Result<(), String> {
+        let g = self.goal(idx)?;
+        if let Goal::Initial(ex, ctx) = &g.goal {
+            let solved = search_goal(ex, &[self.context.to_vec(), ctx.to_vec()].concat(), &env.defs, &env.search)?;
+            self.step(&g.goal, &solved, "auto");
+        }
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<(), String> {
+        if self.states.len() < 2 {
+            return Err("nothing to undo".to_string());
+        }
+        self.states.pop();
+        Ok(())
+    }
+
+    pub fn qed(&self, env: &Environment) -> Result<Proof, String> {
+        let open = self.current().active().len();
+        if open > 0 {
+            return Err(format!("{} goals still open", open));
+        }
+        proof_from_search(recursive_finalize(self.current(), &env.defs),
+                          &self.context, &env.defs, &env.search.system)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse, parse_judgement};
+    use crate::find_term::{SearchOptions};
+
+    #[test]
+    fn tactic_steps() {
+        let env = Environment::blank();
+        let jdg = parse_judgement("A : \\ast, B : \\ast \\vdash x : A \\to B \\to A").unwrap();
+        let mut tac = Tactic::new(&jdg.statement.s_type, &jdg.context);
+        assert_eq!(tac.show(), "A : \\ast, B : \\ast\n?? : A \\to B \\to A\ngoal 1: ?? : A \\to B \\to A");
+        assert!(tac.qed(&env).is_err());
+        assert!(tac.apply("magic", 1, 1, &env).unwrap_err().starts_with("unknown strategy: magic"));
+        assert!(tac.apply("unpack_type_abs", 1, 2, &env).is_err());
+
+        tac.apply("unpack_type_abs", 1, 1, &env).unwrap();
+        tac.apply("unpack_type_abs", 1, 1, &env).unwrap();
+        assert!(tac.alternatives(1, &env).unwrap().starts_with("in_context/1: "));
+        tac.undo().unwrap();
+        tac.auto(1, &env).unwrap();
+        assert_eq!(tac.show(), "no open goals (use :qed)");
+        let proof = tac.qed(&env).unwrap();
+        assert_eq!(proof.lines.last().unwrap().to_latex(),
+                   "A : \\ast, B : \\ast \\vdash \\lambda b : A . \\lambda c : B . b : A \\to B \\to A");
+
+        let env = Environment { search: SearchOptions { limit: 200, ..SearchOptions::standard() }, ..Environment::blank() };
+        let mut tac = Tactic::new(&parse("A").unwrap(), &jdg.context);
+        assert!(tac.auto(1, &env).is_err());
+        assert!(tac.undo().is_err());
+    }
+}
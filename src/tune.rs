@@ -0,0 +1,73 @@
+use crate::model::statement::{Statement};
+use crate::model::expression::{CCExpression};
+use crate::model::def::{Definition};
+use crate::search::proof::{Weights, feature_names};
+use crate::find_term::{SearchOptions, count_expansions};
+
+
+pub struct TuneGoal {
+    pub s_type: CCExpression,
+    pub context: Vec<Statement>
+}
+
+pub fn corpus_cost(corpus: &[TuneGoal], defs: &[Definition],
+                   weights: &Weights, limit: u32) -> u64 {
+    let opts = SearchOptions {
+        weights: weights.clone(),
+        limit,
+        ..SearchOptions::standard()
+    };
+    corpus.iter().map(
+        |g| match count_expansions(&g.s_type, &g.context, defs, &opts) {
+            Ok(count) => count as u64,
+            Err(_) => limit as u64 * 2
+        }).sum()
+}
+
+pub fn tune_weights(corpus: &[TuneGoal], defs: &[Definition],
+                    start: &Weights, limit: u32) -> (Weights, u64) {
+    let mut best = start.clone();
+    let mut best_cost = corpus_cost(corpus, defs, &best, limit);
+    for step in [16, 4, 1] {
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for name in feature_names() {
+                for delta in [step, -step] {
+                    let mut trial = best.clone();
+                    trial.values.insert(name.clone(), best.get(&name) + delta);
+                    let cost = corpus_cost(corpus, defs, &trial, limit);
+                    if cost < best_cost {
+                        best = trial;
+                        best_cost = cost;
+                        improved = true;
+                    }
+                }
+            }
+        }
+    }
+    (best, best_cost)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_judgement};
+
+    #[test]
+    fn tuning_never_worse() {
+        let corpus: Vec<TuneGoal> = [
+            "A : \\ast \\vdash x : A \\to A",
+            "A : \\ast, B : \\ast \\vdash x : A \\to B \\to A",
+            "A : \\ast, B : \\ast \\vdash x : (A \\wedge B) \\to A"
+        ].iter().map(|x| {
+            let jdg = parse_judgement(x).unwrap();
+            TuneGoal { s_type: jdg.statement.s_type, context: jdg.context }
+        }).collect();
+        let start = Weights::standard();
+        let start_cost = corpus_cost(&corpus, &[], &start, 2000);
+        let (_, cost) = tune_weights(&corpus, &[], &start, 2000);
+        assert!(cost <= start_cost);
+    }
+}
@@ -1,9 +1,12 @@
 
 use crate::model::judgement::{Judgement};
+use crate::model::statement::{Statement};
+use crate::model::expression::{CCExpression};
 use crate::model::def::{Definition};
-use crate::model::rules::{all_rules};
-use crate::model::rules::{DerRule, abst_alternatives};
-use crate::model::proof::{LineRef};
+use crate::model::rules::{all_rules_in};
+use crate::model::pts::{PtsSpec};
+use crate::model::rules::{DerRule, PremiseIndex};
+use crate::model::proof::{LineRef, Proof};
 
 
 fn rule_applies_many(jdg: &Judgement,
@@ -54,6 +57,25 @@ fn rule_applies_one(jdg: &Judgement,
     return None;
 }
 
+fn rule_applies_candidates(jdg: &Judgement,
+                           rule: &dyn DerRule,
+                           lines: &[Judgement],
+                           candidates: &[Vec<usize>]
+                           )  -> Option<LineRef> {
+    for premises in candidates {
+        let lhs = premises.first().map(|x| &lines[*x]);
+        let rhs = premises.get(1).map(|x| &lines[*x]);
+        if rule.validate(lhs, rhs, jdg) {
+            return Some(LineRef {
+                rule: rule.name(),
+                line1: premises.first().map(|x| *x as u32),
+                line2: premises.get(1).map(|x| *x as u32)
+            });
+        }
+    }
+    None
+}
+
 fn rule_applies_zero(jdg: &Judgement,
                      rule: &dyn DerRule)  -> Option<LineRef> {
     if rule.validate(None, None, jdg) {
@@ -67,15 +89,112 @@ fn rule_applies_zero(jdg: &Judgement,
 }
 
 
-pub fn check_proof(defs: &[Definition], 
+fn typed_earlier(lines: &[Judgement], context: &[Statement], subject: &CCExpression) -> Option<CCExpression> {
+    lines.iter().find(
+        |j| j.context == context && j.statement.subject.alpha_equiv(subject)
+        ).map(|j| j.statement.s_type.clone())
+}
+
+fn same_type(lhs: &CCExpression, rhs: &CCExpression) -> bool {
+    lhs.beta_reduce().alpha_equiv(&rhs.beta_reduce())
+}
+
+fn explain_line(spec: &PtsSpec, jdg: &Judgement, lines: &[Judgement]) -> String {
+    let ctx = &jdg.context;
+    let s_type = &jdg.statement.s_type;
+    let missing = |ctx: &[Statement], ex: &CCExpression| if !ctx.is_empty() {
+        format!("no earlier line types {} in context {}", ex.to_latex(), Statement::ctx_str(ctx))
+    } else {
+        format!("no earlier line types {}", ex.to_latex())
+    };
+    match &jdg.statement.subject {
+        s if spec.is_sort(s) => format!("sort: {} : {} is not an axiom of {}", s.to_latex(), s_type.to_latex(), spec.name),
+        CCExpression::Var(x) => {
+            match ctx.iter().position(|st| st.subject.var_str().as_ref() == Some(x)) {
+                None => format!("var: {} is not in the context", x),
+                Some(pos) if pos + 1 == ctx.len() => match typed_earlier(lines, &ctx[..pos], &ctx[pos].s_type) {
+                    Some(t) if !spec.is_sort(&t) => format!("var: {} has type {}, which is not a sort", ctx[pos].s_type.to_latex(), t.to_latex()),
+                    Some(_) => format!("var: {} is declared as {}", x, ctx[pos].s_type.to_latex()),
+                    None => format!("var: {}", missing(&ctx[..pos], &ctx[pos].s_type))
+                },
+                Some(_) => format!("weak: {}", missing(&ctx[..ctx.len() - 1], &jdg.statement.subject))
+            }
+        },
+        CCExpression::TypeAbs(x, a, b) => {
+            let inner = [ctx.to_vec(), vec![Statement { subject: CCExpression::Var(x.to_string()), s_type: *a.clone() }]].concat();
+            match (typed_earlier(lines, ctx, a), typed_earlier(lines, &inner, b)) {
+                (None, _) => format!("form: {}", missing(ctx, a)),
+                (_, None) => format!("form: {}", missing(&inner, b)),
+                (Some(s1), Some(s2)) => match spec.rule(&s1, &s2) {
+                    None => format!("form: ({}, {}) is not a rule of {}", s1.to_latex(), s2.to_latex(), spec.name),
+                    Some(s3) => format!("form: the product has sort {}, not {}", s3.to_latex(), s_type.to_latex())
+                }
+            }
+        },
+        CCExpression::Abs(x, a, m) => {
+            let inner = [ctx.to_vec(), vec![Statement { subject: CCExpression::Var(x.to_string()), s_type: *a.clone() }]].concat();
+            match typed_earlier(lines, &inner, m) {
+                None => format!("abst: {}", missing(&inner, m)),
+                Some(b) => {
+                    let prod = CCExpression::TypeAbs(x.to_string(), a.clone(), Box::new(b));
+                    if !same_type(&prod, s_type) {
+                        format!("abst: the abstraction has type {}, not {}", prod.to_latex(), s_type.to_latex())
+                    } else {
+                        format!("abst: {}", missing(ctx, &prod))
+                    }
+                }
+            }
+        },
+        CCExpression::Application(f, a) => {
+            match (typed_earlier(lines, ctx, f), typed_earlier(lines, ctx, a)) {
+                (None, _) => format!("appl: {}", missing(ctx, f)),
+                (_, None) => format!("appl: {}", missing(ctx, a)),
+                (Some(CCExpression::TypeAbs(x, dom, cod)), Some(t)) => {
+                    let res = cod.instantiate(&x, a);
+                    if !same_type(&dom, &t) {
+                        format!("appl: {} has type {}, expected {}", a.to_latex(), t.to_latex(), dom.to_latex())
+                    } else {
+                        format!("appl: the application has type {}, not {}", res.to_latex(), s_type.to_latex())
+                    }
+                },
+                (Some(t), _) => format!("appl: {} has type {}, which is not a product", f.to_latex(), t.to_latex())
+            }
+        },
+        CCExpression::Def(name, _) => format!("inst: no earlier lines match the context and arguments of {}", name),
+        _ => "no matching rule".to_string()
+    }
+}
+
+#[cfg(test)]
+pub fn check_proof(defs: &[Definition],
                    judges: &[Judgement]) -> Result<Vec<LineRef>, String> {
-    let rules = all_rules(defs);
+    check_proof_in(&PtsSpec::coc(), defs, judges)
+}
+
+pub fn check_proof_in(spec: &PtsSpec, defs: &[Definition],
+                      judges: &[Judgement]) -> Result<Vec<LineRef>, String> {
+    check_proof_with(spec, defs, judges, true)
+}
+
+fn check_proof_with(spec: &PtsSpec,
+                    defs: &[Definition],
+                    judges: &[Judgement],
+                    indexed: bool) -> Result<Vec<LineRef>, String> {
+    let rules = all_rules_in(spec, defs);
+    let mut index = PremiseIndex::blank();
     let mut output: Vec<LineRef> = vec![];
     for (idx, jdg) in judges.iter().enumerate() {
         let mut found : Option<LineRef> = None;
         for rule in &rules {
+            let candidates = if indexed && rule.sig_size() <= 2 {
+                rule.candidates(&index, jdg)
+            } else {
+                None
+            };
             if rule.sig_size() == 0 {
                 found = rule_applies_zero(jdg, &(**rule));
+            } else if let Some(c) = candidates {
+                found = rule_applies_candidates(jdg, &(**rule), &judges[0..idx], &c);
             } else if rule.sig_size() == 1 {
                 found = rule_applies_one(jdg, &(**rule), &judges[0..idx]);
             } else if rule.sig_size() == 2 {
@@ -90,21 +209,80 @@ pub fn check_proof(defs: &[Definition],
         if let Some(r) = found {
             output.push(r);
         } else {
-            return Err(format!("No rule applies on line {}: {}",
+            return Err(format!("No rule applies on line {}: {} ({})",
                                idx,
-                               jdg.to_latex()));
+                               jdg.to_latex(),
+                               explain_line(spec, jdg, &judges[0..idx])));
         }
+        index.add(jdg);
     }
 
     return Ok(output);
 }
 
+fn premises_str(line_ref: &LineRef) -> String {
+    [line_ref.line1, line_ref.line2].iter().filter_map(
+        |x| x.map(|l| l.to_string())
+        ).collect::<Vec<String>>().join(",")
+}
+
+fn verify_line(idx: usize, jdg: &Judgement, line_ref: &LineRef,
+               rule: &dyn DerRule, judges: &[Judgement]) -> Result<(), String> {
+    let premises: Vec<u32> = [line_ref.line1, line_ref.line2].iter().filter_map(|x| *x).collect();
+    if let Some(bad) = premises.iter().find(|l| **l as usize >= idx) {
+        return Err(format!("line {}: premise {} is not an earlier line", idx, bad));
+    }
+    let expected = rule.sig_size().min(2) as usize;
+    if premises.len() != expected && rule.sig_size() <= 2 {
+        return Err(format!("line {}: {} takes {} premises, {} given", idx, rule.name(), expected, premises.len()));
+    }
+    let valid = match rule.sig_size() {
+        0 => rule.validate(None, None, jdg),
+        1 => rule.validate(Some(&judges[premises[0] as usize]), None, jdg),
+        2 => rule.validate(Some(&judges[premises[0] as usize]),
+                           Some(&judges[premises[1] as usize]), jdg),
+        _ => rule.validate_premises(&judges[0..idx], &premises, jdg)
+    };
+    if valid {
+        Ok(())
+    } else if premises.is_empty() {
+        Err(format!("line {}: {} does not yield {}", idx, rule.name(), jdg.to_latex()))
+    } else {
+        Err(format!("line {}: {} premises {} do not yield {}",
+                    idx, rule.name(), premises_str(line_ref), jdg.to_latex()))
+    }
+}
+
+#[cfg(test)]
+pub fn verify_proof(defs: &[Definition], proof: &Proof) -> Result<(), String> {
+    verify_proof_in(&PtsSpec::coc(), defs, proof)
+}
+
+pub fn verify_proof_in(spec: &PtsSpec, defs: &[Definition], proof: &Proof) -> Result<(), String> {
+    if proof.lines.len() != proof.refs.len() {
+        return Err(format!("proof has {} lines but {} justifications",
+                           proof.lines.len(), proof.refs.len()));
+    }
+    let rules = all_rules_in(spec, defs);
+    for (idx, (jdg, line_ref)) in proof.lines.iter().zip(&proof.refs).enumerate() {
+        match rules.iter().find(|r| r.name() == line_ref.rule) {
+            Some(rule) => verify_line(idx, jdg, line_ref, &(**rule), &proof.lines)?,
+            None => {
+                return Err(format!("line {}: unknown rule {}", idx, line_ref.rule));
+            }
+        }
+    }
+    Ok(())
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::parser::{parse_judgement, parse_definition};
-    use crate::model::rules::all_rules;
+    use crate::model::rules::{all_rules, abst_alternatives};
+    use crate::unpack_term::{unpack_term};
+    use crate::model::expression::{CCExpression};
 
     #[test]
     fn simple_type_check() {
@@ -292,5 +470,145 @@ mod tests {
 
         assert!(rule.validate_many(&[jdg1], &jdg2).is_some());
     }
-}
 
+    #[test]
+    fn verify_supplied_refs() {
+        let lines: Vec<Judgement> = vec![
+            parse_judgement("\\vdash \\ast : \\square").unwrap(),
+            parse_judgement("A : \\ast \\vdash A : \\ast").unwrap(),
+            parse_judgement("A : \\ast, x : A \\vdash A : \\ast").unwrap(),
+            parse_judgement("A : \\ast, x : A \\vdash x : A").unwrap(),
+            parse_judgement("A : \\ast \\vdash \\prod x  : A . A : \\ast").unwrap(),
+            parse_judgement("A : \\ast \\vdash \\lambda x : A . x : \\prod x  : A . A").unwrap()
+        ];
+        let refs = check_proof(&[], &lines).unwrap();
+        let mut proof = Proof { lines, refs };
+        assert_eq!(verify_proof(&[], &proof), Ok(()));
+
+        proof.refs[5] = LineRef { rule: "abst".to_string(), line1: Some(2), line2: Some(4) };
+        assert_eq!(verify_proof(&[], &proof).unwrap_err(),
+                   "line 5: abst premises 2,4 do not yield A : \\ast \\vdash \\lambda x : A . x : A \\to A");
+        proof.refs[5] = LineRef { rule: "abst".to_string(), line1: Some(3), line2: Some(5) };
+        assert_eq!(verify_proof(&[], &proof).unwrap_err(), "line 5: premise 5 is not an earlier line");
+        proof.refs[5] = LineRef { rule: "abst".to_string(), line1: Some(3), line2: None };
+        assert_eq!(verify_proof(&[], &proof).unwrap_err(), "line 5: abst takes 2 premises, 1 given");
+        proof.refs[5] = LineRef { rule: "magic".to_string(), line1: None, line2: None };
+        assert_eq!(verify_proof(&[], &proof).unwrap_err(), "line 5: unknown rule magic");
+        proof.refs.pop();
+        assert!(verify_proof(&[], &proof).is_err());
+    }
+
+    #[test]
+    fn verify_inst_refs() {
+        let defs = [parse_definition(
+            "A : \\ast \\vartriangleright id \\langle A \\rangle := \\lambda x : A . x : \\prod x : A . A"
+            ).unwrap()];
+        let lines: Vec<Judgement> = vec![
+            parse_judgement("\\vdash \\ast : \\square").unwrap(),
+            parse_judgement("I : \\ast \\vdash I : \\ast").unwrap(),
+            parse_judgement("I : \\ast, x : I \\vdash I : \\ast").unwrap(),
+            parse_judgement("I : \\ast, x : I \\vdash x : I").unwrap(),
+            parse_judgement("I : \\ast, x : I \\vdash id \\langle I \\rangle : \\prod x : I. I").unwrap(),
+            parse_judgement("I : \\ast, x : I \\vdash id \\langle I \\rangle  x : I").unwrap(),
+        ];
+        let refs = check_proof(&defs, &lines).unwrap();
+        let mut proof = Proof { lines, refs };
+        assert_eq!(verify_proof(&defs, &proof), Ok(()));
+        assert_eq!(proof.refs[4].to_latex(), "inst 1");
+        proof.refs[4].line1 = Some(2);
+        assert_eq!(verify_proof(&defs, &proof), Ok(()));
+        proof.refs[4].line1 = Some(3);
+        assert_eq!(verify_proof(&defs, &proof).unwrap_err(),
+                   "line 4: inst premises 3 do not yield I : \\ast, x : I \\vdash id \\langle I \\rangle : I \\to I");
+        proof.refs[4].line1 = None;
+        assert!(verify_proof(&defs, &proof).is_err());
+    }
+
+    fn unpacked_lines(jdg_str: &str, defs: &[Definition]) -> Vec<Judgement> {
+        let jdg = parse_judgement(jdg_str).unwrap();
+        unpack_term(&jdg.statement.subject, &jdg.context, defs).unwrap()
+    }
+
+    #[test]
+    fn indexed_matches_scan() {
+        let defs = [parse_definition("A : \\ast \\vartriangleright lem \\langle A \\rangle := \\independent : \\neg A \\vee A").unwrap()];
+        let cases = [
+            "A : \\ast \\vdash \\lambda b : A . \\lambda a : \\neg A . a b : A \\to \\neg \\neg A",
+            "A : \\ast, B : \\ast \\vdash \\lambda c : A \\wedge B . c A (\\lambda d : A . \\lambda b : B . d) : (A \\wedge B) \\to A",
+            "C : \\ast, A : \\ast, B : \\ast \\vdash \\lambda b : A \\to B . \\lambda c : B \\to C . \\lambda d : A . c (b d) : (A \\to B) \\to (B \\to C) \\to A \\to C",
+            "D : \\ast \\vdash \\lambda b : \\neg \\neg D . lem \\langle D \\rangle D (\\lambda c : \\neg D . b c D) (\\lambda c : D . c) : \\neg \\neg D \\to D"
+        ];
+        for case in cases {
+            let lines = unpacked_lines(case, &defs);
+            let indexed = check_proof_with(&PtsSpec::coc(), &defs, &lines, true);
+            assert!(indexed.is_ok());
+            assert_eq!(indexed, check_proof_with(&PtsSpec::coc(), &defs, &lines, false));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_check_proof() {
+        for n in [10, 20, 40, 80] {
+            let body = (0..n).fold("a".to_string(), |acc, _| format!("f ({})", acc));
+            let lines = unpacked_lines(
+                &format!("A : \\ast, f : A \\to A \\vdash \\lambda a : A . {} : A \\to A", body), &[]);
+            let t0 = std::time::Instant::now();
+            let indexed = check_proof_with(&PtsSpec::coc(), &[], &lines, true);
+            let t_indexed = t0.elapsed();
+            let t1 = std::time::Instant::now();
+            let scan = check_proof_with(&PtsSpec::coc(), &[], &lines, false);
+            let t_scan = t1.elapsed();
+            assert_eq!(indexed, scan);
+            println!("{} lines: indexed {:?}, scan {:?}", lines.len(), t_indexed, t_scan);
+        }
+    }
+
+    #[test]
+    fn check_in_lambda_cube() {
+        let simple = unpacked_lines("A : \\ast \\vdash \\lambda a : A . a : A \\to A", &[]);
+        let poly = unpacked_lines("\\vdash \\lambda A : \\ast . \\lambda a : A . a : \\prod A : \\ast . A \\to A", &[]);
+        for name in PtsSpec::preset_names() {
+            let spec = PtsSpec::preset(&name).unwrap();
+            assert!(check_proof_in(&spec, &[], &simple).is_ok());
+            let refs = check_proof_in(&spec, &[], &poly);
+            assert_eq!(refs.is_ok(), spec.rule(&CCExpression::Sq, &CCExpression::Star).is_some());
+            if let Ok(r) = refs {
+                let proof = Proof { lines: poly.clone(), refs: r };
+                assert_eq!(verify_proof_in(&spec, &[], &proof), Ok(()));
+                assert!(verify_proof_in(&PtsSpec::stlc(), &[], &proof).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn explain_failed_lines() {
+        let explain = |lines: &[&str]| {
+            let jdgs: Vec<Judgement> = lines.iter().map(|x| parse_judgement(x).unwrap()).collect();
+            let msg = check_proof(&[], &jdgs).unwrap_err();
+            msg[msg.find(" (").unwrap() + 2..msg.len() - 1].to_string()
+        };
+        assert_eq!(explain(&["\\vdash \\square : \\ast"]), "sort: \\square : \\ast is not an axiom of coc");
+        assert_eq!(explain(&["\\vdash x : A"]), "var: x is not in the context");
+        assert_eq!(explain(&["A : \\ast \\vdash A : \\ast"]), "var: no earlier line types \\ast");
+        assert_eq!(explain(&["\\vdash \\ast : \\square", "A : \\ast \\vdash A : \\ast",
+                             "A : \\ast \\vdash \\lambda x : A . x : A \\to A"]),
+                   "abst: no earlier line types x in context A : \\ast, x : A");
+        assert_eq!(explain(&["\\vdash \\ast : \\square", "A : \\ast \\vdash A : \\ast",
+                             "A : \\ast, a : A \\vdash a : A", "A : \\ast, a : A \\vdash a a : A"]),
+                   "appl: a has type A, which is not a product");
+    }
+
+    #[test]
+    fn cumulative_universes() {
+        let lines: Vec<Judgement> = [
+            "\\vdash \\ast : \\square",
+            "\\vdash \\mathrm{Type}_{1} : \\mathrm{Type}_{2}",
+            "\\vdash \\ast : \\mathrm{Type}_{1}"
+        ].iter().map(|x| parse_judgement(x).unwrap()).collect();
+        let refs = check_proof_in(&PtsSpec::ecc(), &[], &lines).unwrap();
+        assert_eq!(refs.iter().map(|x| x.rule.clone()).collect::<Vec<String>>(),
+                   ["sort", "sort", "conv"]);
+        assert!(check_proof_in(&PtsSpec::coc(), &[], &lines).is_err());
+    }
+}
@@ -1,10 +1,12 @@
+use std::collections::HashMap;
 use crate::model::judgement::{Judgement};
 use crate::model::statement::{Statement};
 use crate::model::expression::{CCExpression};
 use crate::model::def::{Definition};
 use crate::model::rules::{do_type_sub};
+use crate::model::pts::{PtsSpec};
 
-fn unpack_remaining_context(ctx: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+fn unpack_remaining_context(ctx: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
     if ctx.len() == 0 { return Ok(vec![]); }
     let current = (*ctx.last().as_ref().unwrap()).clone();
     let last = Judgement {
@@ -14,30 +16,31 @@ fn unpack_remaining_context(ctx: &[Statement], defs: &[Definition]) -> Result<Ve
     };
     let remainder = ctx[0..ctx.len()-1].to_vec();
 
-    let other = unpack_remaining_context(&remainder, defs);
-
     let extra_type = if current.subject.is_var()
         && (Statement::subject_in_context(&current.s_type, ctx) || current.s_type.is_sort())  {
             Ok(vec![])
     } else {
-        unpack_term(&current.s_type, &ctx[0..ctx.len()-1], defs)
+        unpack_memo(&current.s_type, &remainder, defs, memo)
     };
 
-    match (other, extra_type) {
-        (Ok(lst), Ok(ext)) => Ok([lst, ext, vec![last]].concat()),
-        (Err(msg), _) => Err(msg),
-        (_, Err(msg)) => Err(msg),
+    match extra_type {
+        Ok(ext) if !ext.is_empty() => Ok(remove_dup(ext.iter().chain(std::iter::once(&last)))),
+        Ok(_) => unpack_remaining_context(&remainder, defs, memo).map(|lst| [lst, vec![last]].concat()),
+        Err(msg) => Err(msg),
     }
 }
 
 
-fn unpack_star(context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
-    let stmt = Statement {
-        subject: CCExpression::Star,
-        s_type: CCExpression::Sq
-    };
+fn sort_axiom(sort: &CCExpression, memo: &Memo) -> Result<Statement, String> {
+    memo.spec.axiom_of(sort).map(
+        |s_type| Statement { subject: sort.clone(), s_type }
+        ).ok_or(format!("Cannot unwrap {} in {}", sort.to_latex(), memo.spec.name))
+}
 
-    let remaining = unpack_remaining_context(context, defs);
+fn unpack_sort(sort: &CCExpression, context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
+    let stmt = sort_axiom(sort, memo)?;
+
+    let remaining = unpack_remaining_context(context, defs, memo);
 
     match remaining {
         Ok(lst) => Ok([vec![Judgement { defs: vec![], statement: stmt, context: vec![] }], lst].concat()),
@@ -46,7 +49,20 @@ fn unpack_star(context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgeme
 }
 
 
-fn unpack_var(var: &str, context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+fn unpack_typed(term: &CCExpression, context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
+    let lines = unpack_memo(term, context, defs, memo)?;
+    if !memo.spec.is_sort(term) || context.is_empty() {
+        return Ok(lines);
+    }
+    let last = Judgement {
+        defs: vec![],
+        context: context.to_vec(),
+        statement: sort_axiom(term, memo)?
+    };
+    Ok(remove_dup(lines.iter().chain(std::iter::once(&last))))
+}
+
+fn unpack_var(var: &str, context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
     let v_type: Option<CCExpression> = context.iter().filter_map(
         |st| if st.subject.var_str() == Some(var.to_string()) {
             return Some(st.s_type.clone());
@@ -71,16 +87,17 @@ fn unpack_var(var: &str, context: &[Statement], defs: &[Definition]) -> Result<V
                 }).collect()
         };
 
-        let res_r = unpack_term(&stmt.s_type, &ctx2, defs);
+        let res_r = unpack_memo(&stmt.s_type, &ctx2, defs, memo);
         if let Err(msg) = res_r {
             let ctx_str = Statement::ctx_str(&ctx2);
             return Err(format!("While unpacking Var ({}), ctx2: [{}], other error:\n\t{}", stmt.s_type.to_latex(), ctx_str, msg));
         }
-        return Ok([res_r.unwrap(), vec![Judgement {
+        let last = Judgement {
             defs: vec![],
             statement: stmt,
             context: context.to_vec()
-        }]].concat());
+        };
+        return Ok(remove_dup(res_r.unwrap().iter().chain(std::iter::once(&last))));
     }
     return Err(format!("failed to unpack Var term: {}, context: [{}]",
                        var,
@@ -88,12 +105,21 @@ fn unpack_var(var: &str, context: &[Statement], defs: &[Definition]) -> Result<V
                        ));
 }
 
+fn sort_of(term: &CCExpression, context: &[Statement], lines: &[Judgement], memo: &Memo) -> CCExpression {
+    if let Some(s) = memo.spec.axiom_of(term) {
+        return s;
+    }
+    lines.iter().rev().find(
+        |j| j.context == context && j.statement.subject == *term
+        ).unwrap_or(lines.last().unwrap()).statement.s_type.clone()
+}
+
 fn unpack_type_abs(var: &str, v_type: &CCExpression, ret: &CCExpression,
-                   context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+                   context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
     let absts = Statement::abstractions(ret);
     let new_var = Statement::next_unused_var(&[context, &absts].concat());
 
-    let p1_r = unpack_term(v_type, context, defs);
+    let p1_r = unpack_memo(v_type, context, defs, memo);
     if let Err(msg) = p1_r {
         let ctx_str = Statement::ctx_str(context);
         return Err(format!("While unpacking TypeAbs ({}/{}/{}), ctx: [{}], other error:\n\t{}", var, v_type.to_latex(), ret.to_latex(), ctx_str, msg));
@@ -106,13 +132,20 @@ fn unpack_type_abs(var: &str, v_type: &CCExpression, ret: &CCExpression,
     };
     let new_ctx = [context, &vec![stmt.clone()]].concat();
 
-    let p2_r = unpack_term(&ret.substitute(var, &stmt.subject), &new_ctx, defs);
+    let p2_r = unpack_typed(&ret.substitute(var, &stmt.subject), &new_ctx, defs, memo);
     if let Err(msg) = p2_r {
         let ctx_str = Statement::ctx_str(&new_ctx);
         return Err(format!("While unpacking TypeAbs ret ({}/{}/{}), ctx: [{}], other error:\n\t{}", var, v_type.to_latex(), ret.to_latex(), ctx_str, msg));
     }
     let p2 = p2_r.unwrap();
 
+    let ret_sub = ret.substitute(var, &stmt.subject);
+    let s1 = sort_of(v_type, context, &p1, memo);
+    let s2 = sort_of(&ret_sub, &new_ctx, &p2, memo);
+    let s_type = memo.spec.rule(&s1, &s2).ok_or(format!(
+            "product ({}, {}) not allowed in {}: {}", s1.to_latex(), s2.to_latex(), memo.spec.name,
+            CCExpression::TypeAbs(var.to_string(), Box::new(v_type.clone()), Box::new(ret.clone())).to_latex()))?;
+
     let last = Judgement {
         defs: vec![],
         context: context.to_vec(),
@@ -120,7 +153,7 @@ fn unpack_type_abs(var: &str, v_type: &CCExpression, ret: &CCExpression,
             subject: CCExpression::TypeAbs(stmt.subject.var_str().unwrap().to_string(),
                                            Box::new(v_type.clone()),
                                            Box::new(ret.substitute(var, &stmt.subject))),
-            s_type: p2.last().unwrap().statement.s_type.clone()
+            s_type
         }
     };
 
@@ -134,18 +167,18 @@ fn def_arg_type_eq(def: &Definition, args: &Vec<Vec<Judgement>>) -> bool {
         return false;
     }
     let d_types = tl_o.unwrap();
-    let a_types: Vec<&CCExpression> = args.iter().map(
-        |arg_list| &arg_list.last().unwrap().statement.s_type).collect();
+    let known: Vec<Statement> = args.iter().map(
+        |arg_list| arg_list.last().unwrap().statement.clone()).collect();
 
-    return a_types.iter().zip(d_types).all(
-        |(a, d)| **a == d);
+    known.iter().zip(d_types).enumerate().all(
+        |(idx, (a, d))| a.s_type.alpha_equiv(&do_type_sub(&d, def, &known[..idx].to_vec())))
 }
 
 fn unpack_def(name: &str, args: &[CCExpression],
-                   context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+                   context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
 
     let recur_res: Vec<Result<Vec<Judgement>, String>> = args.iter().map(
-        |x| unpack_term(x, context, defs)
+        |x| unpack_memo(x, context, defs, memo)
         ).collect();
     if recur_res.iter().any(|x| x.is_err()) {
         return Err(format!("While unpacking Def ({}), other error:\n\t{}", name, recur_res.iter().find(|x| x.is_err()).as_ref().unwrap().as_ref().unwrap_err().to_string()));
@@ -176,7 +209,7 @@ fn unpack_def(name: &str, args: &[CCExpression],
 }
 
 fn unpack_abs(var: &str, v_type: &CCExpression, ret: &CCExpression,
-                   context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+                   context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
     let absts = Statement::abstractions(ret);
     let new_var = Statement::next_unused_var(&[context, &absts].concat());
     let c_stmt = Statement {
@@ -184,7 +217,7 @@ fn unpack_abs(var: &str, v_type: &CCExpression, ret: &CCExpression,
         s_type: v_type.clone()
     };
     let new_ctx = [context, &vec![c_stmt.clone()]].concat();
-    let p1_r = unpack_term(&ret.substitute(var, &c_stmt.subject), &new_ctx, defs);
+    let p1_r = unpack_typed(&ret.substitute(var, &c_stmt.subject), &new_ctx, defs, memo);
     if let Err(msg) = p1_r {
         let ctx_str = Statement::ctx_str(&new_ctx);
         return Err(format!("While unpacking Abs ({}/{}/{}), ctx: [{}], other error:\n\t{}", var, v_type.to_latex(), ret.to_latex(), ctx_str, msg));
@@ -198,7 +231,7 @@ fn unpack_abs(var: &str, v_type: &CCExpression, ret: &CCExpression,
         Box::new(new_ret.clone())
     );
 
-    let p2_r = unpack_term(&new_type, context, defs);
+    let p2_r = unpack_memo(&new_type, context, defs, memo);
     if let Err(msg) = p2_r {
         let ctx_str = Statement::ctx_str(context);
         return Err(format!("While unpacking Abs ret ({}/{}/{}), ctx: [{}], other error:\n\t{}", var, v_type.to_latex(), ret.to_latex(), ctx_str, msg));
@@ -219,34 +252,38 @@ fn unpack_abs(var: &str, v_type: &CCExpression, ret: &CCExpression,
 }
 
 fn unpack_appl(lhs: &CCExpression, rhs: &CCExpression,
-                   context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
-    let p1_r = unpack_term(lhs, context, defs);
-    let p2_r = unpack_term(rhs, context, defs);
+                   context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
+    let p1_r = unpack_memo(lhs, context, defs, memo);
     let ctx_str = Statement::ctx_str(context);
     if let Err(msg) = p1_r { return Err(format!(
                 "While unpacking Appl({}/{}), ctx: [{}], other error:\n\t{}",
                 lhs.to_latex(), rhs.to_latex(), ctx_str, msg)); }
-    if let Err(msg) = p2_r { return Err(format!(
-                "While unpacking Appl({}/{}), ctx: [{}], other error:\n\t{}",
-                lhs.to_latex(), rhs.to_latex(), ctx_str, msg)); }
 
     let p1 = p1_r.unwrap();
-    let p2 = p2_r.unwrap();
 
     let ret_type = p1.last().unwrap().statement.s_type.clone();
-    if let CCExpression::TypeAbs(x, _v_type, inner_ret) = ret_type {
+    if let CCExpression::TypeAbs(x, v_type, inner_ret) = ret_type {
+        let p2_r = unpack_memo_as(rhs, &v_type, context, defs, memo);
+        if let Err(msg) = p2_r { return Err(format!(
+                    "While unpacking Appl({}/{}), ctx: [{}], other error:\n\t{}",
+                    lhs.to_latex(), rhs.to_latex(), ctx_str, msg)); }
+        let p2 = p2_r.unwrap();
+
         let last = Judgement {
             defs: vec![],
             context: context.to_vec(),
             statement: Statement {
                 subject: CCExpression::Application(Box::new(lhs.clone()),
                                                    Box::new(rhs.clone())),
-                s_type: inner_ret.substitute(&x, rhs)
+                s_type: inner_ret.instantiate(&x, rhs)
             }
         };
 
         return Ok(remove_dup(p2.iter().chain(&p1).chain(std::iter::once(&last))));
     } else {
+        if let Err(msg) = unpack_memo(rhs, context, defs, memo) { return Err(format!(
+                    "While unpacking Appl({}/{}), ctx: [{}], other error:\n\t{}",
+                    lhs.to_latex(), rhs.to_latex(), ctx_str, msg)); }
         return Err(format!("failed to unpack Appl term: ({}) ({})", lhs.to_latex(), rhs.to_latex()));
     }
 }
@@ -266,26 +303,79 @@ where T: Iterator<Item= &'a Judgement>
 }
 
 
-pub fn unpack_term(term: &CCExpression, context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+struct Memo<'a> {
+    spec: &'a PtsSpec,
+    lines: HashMap<String, Vec<Judgement>>
+}
 
-    match term {
-        CCExpression::Star => unpack_star(context, defs),
-        CCExpression::Sq => Err("Cannot unwrap Sq".to_string()),
+impl Memo<'_> {
+    fn new(spec: &PtsSpec) -> Memo<'_> {
+        Memo { spec, lines: HashMap::new() }
+    }
+}
+
+fn unpack_memo(term: &CCExpression, context: &[Statement], defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
+    let key = format!("{} \\vdash {}", Statement::ctx_str(context), term.to_latex());
+    if let Some(lines) = memo.lines.get(&key) {
+        return Ok(lines.clone());
+    }
+    let lines = match term {
+        CCExpression::Star | CCExpression::Sq | CCExpression::Universe(_) => unpack_sort(term, context, defs, memo),
         CCExpression::Prim => Err("Cannot unwrap Prim".to_string()),
-        CCExpression::Var(x) => unpack_var(&x, context, defs),
-        CCExpression::Def(name, args) => unpack_def(&name, args, context, defs),
-        CCExpression::Abs(x, v_type, ret) => unpack_abs(&x, &v_type, &ret, context, defs),
-        CCExpression::TypeAbs(x, v_type, ret) => unpack_type_abs(&x, &v_type, &ret, context, defs),
-        CCExpression::Application(lhs, rhs) => unpack_appl(&lhs, &rhs, context, defs)
+        CCExpression::Var(x) => unpack_var(x, context, defs, memo),
+        CCExpression::Def(name, args) => unpack_def(name, args, context, defs, memo),
+        CCExpression::Abs(x, v_type, ret) => unpack_abs(x, v_type, ret, context, defs, memo),
+        CCExpression::TypeAbs(x, v_type, ret) => unpack_type_abs(x, v_type, ret, context, defs, memo),
+        CCExpression::Application(lhs, rhs) => unpack_appl(lhs, rhs, context, defs, memo)
+    }?;
+    memo.lines.insert(key, lines.clone());
+    Ok(lines)
+}
+
+fn unpack_memo_as(term: &CCExpression, s_type: &CCExpression, context: &[Statement],
+                  defs: &[Definition], memo: &mut Memo) -> Result<Vec<Judgement>, String> {
+    let lines = unpack_memo(term, context, defs, memo)?;
+    let found = lines.last().unwrap().statement.s_type.clone();
+    if found.alpha_equiv(s_type) {
+        return Ok(lines);
     }
+    // only compare against a stated type that is itself well typed
+    let t_lines = match unpack_memo(s_type, context, defs, memo) {
+        Ok(t_lines) if t_lines.last().unwrap().statement.s_type.is_sort() => t_lines,
+        _ => return Ok(lines)
+    };
+    if !memo.spec.subtype(&found, s_type) {
+        return Ok(lines);
+    }
+    let last = Judgement {
+        defs: vec![],
+        context: context.to_vec(),
+        statement: Statement { subject: term.clone(), s_type: s_type.clone() }
+    };
+    Ok(remove_dup(lines.iter().chain(t_lines.iter()).chain(std::iter::once(&last))))
+}
+
+#[cfg(test)]
+pub fn unpack_term(term: &CCExpression, context: &[Statement], defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+    unpack_term_in(&PtsSpec::coc(), term, context, defs)
+}
+
+pub fn unpack_term_in(spec: &PtsSpec, term: &CCExpression, context: &[Statement],
+                      defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+    unpack_memo(term, context, defs, &mut Memo::new(spec))
+}
+
+pub fn unpack_term_as(spec: &PtsSpec, term: &CCExpression, s_type: &CCExpression, context: &[Statement],
+                      defs: &[Definition]) -> Result<Vec<Judgement>, String> {
+    unpack_memo_as(term, s_type, context, defs, &mut Memo::new(spec))
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::{parse_judgement, parse_definition};
-    use crate::type_check::{check_proof};
+    use crate::parser::{parse, parse_judgement, parse_definition};
+    use crate::type_check::{check_proof, check_proof_in};
 
     #[test]
     fn simple_unpack() {
@@ -441,5 +531,41 @@ mod tests {
                    "var 14"
         ]);
     }
-}
 
+    #[test]
+    fn conv_unpack() {
+        let jdg: Judgement = parse_judgement(
+            "A:\\ast, f : ((\\lambda T : \\ast . T) A) \\to A, a : A \\vdash f a : A").unwrap();
+        let lines = unpack_term(&jdg.statement.subject, &jdg.context, &[]).unwrap();
+        assert_eq!(lines.last().unwrap().to_latex(), jdg.to_latex());
+        let refs = check_proof(&[], &lines).unwrap();
+        assert!(refs.iter().any(|x| x.rule == "conv"));
+
+        let a_type = parse("(\\lambda T : \\ast . T) A").unwrap();
+        let lines = unpack_term_as(&PtsSpec::coc(), &CCExpression::Var("a".to_string()), &a_type, &jdg.context, &[]).unwrap();
+        assert_eq!(lines.last().unwrap().statement.s_type, a_type);
+        assert_eq!(check_proof(&[], &lines).unwrap().last().unwrap().rule, "conv");
+    }
+
+    #[test]
+    fn star_body_unpack() {
+        let jdg: Judgement = parse_judgement("A:\\ast \\vdash A \\to \\ast : \\square").unwrap();
+        let lines = unpack_term(&jdg.statement.subject, &jdg.context, &[]).unwrap();
+        assert!(lines.iter().any(|x| x.to_latex() == "A : \\ast, a : A \\vdash \\ast : \\square"));
+        let refs = check_proof(&[], &lines).unwrap();
+        assert_eq!(lines.len(), refs.len());
+    }
+
+    #[test]
+    fn unpack_in_system() {
+        let poly = parse("\\prod A : \\ast . A \\to A").unwrap();
+        let err = unpack_term_in(&PtsSpec::stlc(), &poly, &[], &[]).unwrap_err();
+        assert!(err.starts_with("product (\\square, \\ast) not allowed in stlc"));
+        let lines = unpack_term_in(&PtsSpec::lambda2(), &poly, &[], &[]).unwrap();
+        assert_eq!(check_proof_in(&PtsSpec::lambda2(), &[], &lines).unwrap().len(), lines.len());
+
+        let lines = unpack_term_in(&PtsSpec::ecc(), &CCExpression::Sq, &[], &[]).unwrap();
+        assert_eq!(lines.last().unwrap().to_latex(), "\\vdash \\square : \\mathrm{Type}_{1}");
+        assert!(unpack_term(&CCExpression::Sq, &[], &[]).is_err());
+    }
+}